@@ -0,0 +1,139 @@
+//! Client for anchoring offchain chains on chain.
+use async_trait::async_trait;
+use parity_scale_codec::{Decode, Encode};
+use std::marker::PhantomData;
+use substrate_subxt::sp_runtime::traits::{AtLeast32BitUnsigned, Member};
+use substrate_subxt::system::{System, SystemEventsDecoder};
+use substrate_subxt::{module, Call, Event, Runtime, SignedExtension, SignedExtra, Store};
+use sunshine_client_utils::codec::hasher::{TreeHashBlake2b256, TreeHasherBlake2b256};
+use sunshine_client_utils::codec::trie::SealedBlock;
+use sunshine_client_utils::{Client, Node, Result};
+use thiserror::Error;
+
+#[module]
+pub trait Chain: System {
+    /// Identifier of an offchain chain.
+    type ChainId: Member + AtLeast32BitUnsigned + Encode + Decode + Default + Copy;
+
+    /// Block number of an offchain chain.
+    type Number: Member + AtLeast32BitUnsigned + Encode + Decode + Default + Copy;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Store)]
+pub struct AuthorsStore<T: Chain> {
+    #[store(returns = Option<<T as System>::AccountId>)]
+    pub chain_id: T::ChainId,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Encode, Store)]
+pub struct HeadsStore<T: Chain> {
+    #[store(returns = Option<(TreeHashBlake2b256, T::Number)>)]
+    pub chain_id: T::ChainId,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Call, Encode)]
+pub struct CreateChainCall<T: Chain> {
+    pub _runtime: PhantomData<T>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Call, Encode)]
+pub struct AuthorBlockCall<'a, T: Chain> {
+    pub chain_id: T::ChainId,
+    pub root: &'a TreeHashBlake2b256,
+    pub proof: &'a [Vec<u8>],
+    pub proof_data: &'a [(String, Option<Vec<u8>>)],
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct NewChainEvent<T: Chain> {
+    pub chain_id: T::ChainId,
+    pub author: <T as System>::AccountId,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Event, Decode)]
+pub struct NewBlockEvent<T: Chain> {
+    pub chain_id: T::ChainId,
+    pub number: T::Number,
+    pub author: <T as System>::AccountId,
+}
+
+/// Client extension for anchoring offchain chains.
+#[async_trait]
+pub trait ChainClient<N: Node>: Client<N>
+where
+    N::Runtime: Chain,
+{
+    /// Creates a new chain authored by the signer.
+    async fn create_chain(&self) -> Result<<N::Runtime as Chain>::ChainId>;
+
+    /// Submits the proof of a sealed block and returns the new block number.
+    async fn author_block(
+        &self,
+        chain_id: <N::Runtime as Chain>::ChainId,
+        block: &SealedBlock<TreeHasherBlake2b256>,
+    ) -> Result<<N::Runtime as Chain>::Number>;
+
+    /// Returns the root and number of the latest anchored block.
+    async fn head(
+        &self,
+        chain_id: <N::Runtime as Chain>::ChainId,
+    ) -> Result<Option<(TreeHashBlake2b256, <N::Runtime as Chain>::Number)>>;
+}
+
+#[async_trait]
+impl<N, C> ChainClient<N> for C
+where
+    N: Node,
+    N::Runtime: Chain,
+    <N::Runtime as System>::AccountId: Into<<N::Runtime as System>::Address>,
+    <<<N::Runtime as Runtime>::Extra as SignedExtra<N::Runtime>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync,
+    C: Client<N>,
+{
+    async fn create_chain(&self) -> Result<<N::Runtime as Chain>::ChainId> {
+        let signer = self.chain_signer()?;
+        let event = self
+            .chain_client()
+            .create_chain_and_watch(&signer)
+            .await?
+            .new_chain()?
+            .ok_or(NewChainEventFind)?;
+        Ok(event.chain_id)
+    }
+
+    async fn author_block(
+        &self,
+        chain_id: <N::Runtime as Chain>::ChainId,
+        block: &SealedBlock<TreeHasherBlake2b256>,
+    ) -> Result<<N::Runtime as Chain>::Number> {
+        let signer = self.chain_signer()?;
+        let event = self
+            .chain_client()
+            .author_block_and_watch(
+                &signer,
+                chain_id,
+                block.offchain.root(),
+                &block.proof,
+                &block.proof_data,
+            )
+            .await?
+            .new_block()?
+            .ok_or(NewBlockEventFind)?;
+        Ok(event.number)
+    }
+
+    async fn head(
+        &self,
+        chain_id: <N::Runtime as Chain>::ChainId,
+    ) -> Result<Option<(TreeHashBlake2b256, <N::Runtime as Chain>::Number)>> {
+        Ok(self.chain_client().heads(chain_id, None).await?)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Failed to find new chain event")]
+pub struct NewChainEventFind;
+
+#[derive(Debug, Error)]
+#[error("Failed to find new block event")]
+pub struct NewBlockEventFind;
@@ -0,0 +1,306 @@
+//! Anchors the heads of offchain chains on chain.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::weights::Weight;
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, Parameter};
+use frame_system::ensure_signed;
+use parity_scale_codec::Decode;
+use sp_runtime::traits::{AtLeast32BitUnsigned, CheckedAdd, Member, One, Zero};
+use sp_std::prelude::*;
+use sp_trie::Layout;
+use sunshine_codec::hasher::{TreeHashBlake2b256, TreeHasherBlake2b256};
+
+/// Key value pairs proven to be contained in an offchain block.
+pub type ProofData = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// Weight of authoring a block without a proof.
+pub const AUTHOR_BLOCK_WEIGHT: Weight = 10_000;
+
+/// Weight of verifying a byte of the proof and proof data.
+pub const PROOF_BYTE_WEIGHT: Weight = 100;
+
+/// Size of the proof and proof data in bytes.
+pub fn proof_size(proof: &[Vec<u8>], proof_data: &[(Vec<u8>, Option<Vec<u8>>)]) -> u64 {
+    let proof_len: usize = proof.iter().map(Vec::len).sum();
+    let data_len: usize = proof_data
+        .iter()
+        .map(|(k, v)| k.len() + v.as_ref().map(Vec::len).unwrap_or_default())
+        .sum();
+    (proof_len + data_len) as u64
+}
+
+pub trait Trait: frame_system::Trait {
+    /// Identifier of an offchain chain.
+    type ChainId: Parameter + Member + AtLeast32BitUnsigned + Default + Copy;
+
+    /// Block number of an offchain chain.
+    type Number: Parameter + Member + AtLeast32BitUnsigned + Default + Copy;
+
+    /// The overarching event type.
+    type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as Chain {
+        /// Next chain id.
+        pub ChainIdCounter: T::ChainId;
+
+        /// Account allowed to author blocks.
+        pub Authors get(fn authors): map
+            hasher(blake2_128_concat) T::ChainId => Option<T::AccountId>;
+
+        /// Root and number of the latest block.
+        pub Heads get(fn heads): map
+            hasher(blake2_128_concat) T::ChainId => Option<(TreeHashBlake2b256, T::Number)>;
+    }
+}
+
+decl_event!(
+    pub enum Event<T>
+    where
+        AccountId = <T as frame_system::Trait>::AccountId,
+        ChainId = <T as Trait>::ChainId,
+        Number = <T as Trait>::Number,
+    {
+        /// A new chain was created.
+        NewChain(ChainId, AccountId),
+        /// A new block was authored.
+        NewBlock(ChainId, Number, AccountId),
+    }
+);
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// Chain id overflow.
+        ChainIdOverflow,
+        /// Chain doesn't exist.
+        UnknownChain,
+        /// Origin isn't the author of the chain.
+        Unauthorized,
+        /// Proof doesn't match the root.
+        InvalidProof,
+        /// Proof data doesn't contain the key.
+        MissingKey,
+        /// Proof data contains a value that failed to decode.
+        InvalidValue,
+        /// Block number isn't the successor of the head.
+        InvalidNumber,
+        /// Ancestor isn't the root of the head.
+        InvalidAncestor,
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
+        fn deposit_event() = default;
+
+        #[weight = 10_000]
+        pub fn create_chain(origin) {
+            let author = ensure_signed(origin)?;
+            let chain_id = <ChainIdCounter<T>>::get();
+            let next = chain_id
+                .checked_add(&One::one())
+                .ok_or(Error::<T>::ChainIdOverflow)?;
+            <ChainIdCounter<T>>::put(next);
+            <Authors<T>>::insert(chain_id, &author);
+            Self::deposit_event(RawEvent::NewChain(chain_id, author));
+        }
+
+        /// Verifies the proof of a sealed block and makes it the new head.
+        ///
+        /// The proof data needs to contain the `number` and `ancestor` keys.
+        /// The weight grows with the size of the proof.
+        #[weight = AUTHOR_BLOCK_WEIGHT
+            .saturating_add(PROOF_BYTE_WEIGHT.saturating_mul(proof_size(proof, proof_data)))]
+        pub fn author_block(
+            origin,
+            chain_id: T::ChainId,
+            root: TreeHashBlake2b256,
+            proof: Vec<Vec<u8>>,
+            proof_data: ProofData,
+        ) {
+            let author = ensure_signed(origin)?;
+            let owner = <Authors<T>>::get(chain_id).ok_or(Error::<T>::UnknownChain)?;
+            ensure!(owner == author, Error::<T>::Unauthorized);
+
+            sp_trie::verify_trie_proof::<Layout<TreeHasherBlake2b256>, _, _, _>(
+                &root,
+                &proof,
+                &proof_data,
+            )
+            .map_err(|_| Error::<T>::InvalidProof)?;
+            let number: T::Number = Self::proof_value(&proof_data, b"number")?;
+            let ancestor: Option<TreeHashBlake2b256> = Self::proof_value(&proof_data, b"ancestor")?;
+
+            if let Some((head, head_number)) = <Heads<T>>::get(chain_id) {
+                let next = head_number.checked_add(&One::one());
+                ensure!(next == Some(number), Error::<T>::InvalidNumber);
+                ensure!(ancestor == Some(head), Error::<T>::InvalidAncestor);
+            } else {
+                ensure!(number.is_zero(), Error::<T>::InvalidNumber);
+                ensure!(ancestor.is_none(), Error::<T>::InvalidAncestor);
+            }
+
+            <Heads<T>>::insert(chain_id, (root, number));
+            Self::deposit_event(RawEvent::NewBlock(chain_id, number, author));
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    fn proof_value<V: Decode>(
+        proof_data: &[(Vec<u8>, Option<Vec<u8>>)],
+        key: &[u8],
+    ) -> Result<V, Error<T>> {
+        let value = proof_data
+            .iter()
+            .find(|(k, _)| k.as_slice() == key)
+            .and_then(|(_, v)| v.as_ref())
+            .ok_or(Error::<T>::MissingKey)?;
+        V::decode(&mut &value[..]).map_err(|_| Error::<T>::InvalidValue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_support::weights::GetDispatchInfo;
+    use frame_support::{assert_noop, assert_ok, impl_outer_origin, parameter_types};
+    use parity_scale_codec::Encode;
+    use sp_core::H256;
+    use sp_runtime::testing::Header;
+    use sp_runtime::traits::{BlakeTwo256, IdentityLookup};
+    use sp_runtime::Perbill;
+    use sunshine_codec::trie::{BlockBuilder, SealedBlock};
+
+    impl_outer_origin! {
+        pub enum Origin for Test where system = frame_system {}
+    }
+
+    #[derive(Clone, Eq, PartialEq)]
+    pub struct Test;
+
+    parameter_types! {
+        pub const BlockHashCount: u64 = 250;
+        pub const MaximumBlockWeight: Weight = 1024;
+        pub const MaximumBlockLength: u32 = 2 * 1024;
+        pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    }
+
+    impl frame_system::Trait for Test {
+        type BaseCallFilter = ();
+        type Origin = Origin;
+        type Call = ();
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<Self::AccountId>;
+        type Header = Header;
+        type Event = ();
+        type BlockHashCount = BlockHashCount;
+        type MaximumBlockWeight = MaximumBlockWeight;
+        type DbWeight = ();
+        type BlockExecutionWeight = ();
+        type ExtrinsicBaseWeight = ();
+        type MaximumExtrinsicWeight = MaximumBlockWeight;
+        type MaximumBlockLength = MaximumBlockLength;
+        type AvailableBlockRatio = AvailableBlockRatio;
+        type Version = ();
+        type PalletInfo = ();
+        type AccountData = ();
+        type OnNewAccount = ();
+        type OnKilledAccount = ();
+        type SystemWeightInfo = ();
+    }
+
+    impl Trait for Test {
+        type ChainId = u64;
+        type Number = u64;
+        type Event = ();
+    }
+
+    type ChainModule = Module<Test>;
+
+    fn new_test_ext() -> sp_io::TestExternalities {
+        frame_system::GenesisConfig::default()
+            .build_storage::<Test>()
+            .unwrap()
+            .into()
+    }
+
+    fn seal(
+        number: u64,
+        ancestor: Option<TreeHashBlake2b256>,
+    ) -> SealedBlock<TreeHasherBlake2b256> {
+        let mut block = BlockBuilder::new();
+        block.insert("number".into(), &number, true);
+        block.insert("ancestor".into(), &ancestor, true);
+        block.insert("payload".into(), &number.to_string(), false);
+        block.seal().unwrap()
+    }
+
+    fn author_block(
+        author: u64,
+        block: &SealedBlock<TreeHasherBlake2b256>,
+    ) -> frame_support::dispatch::DispatchResult {
+        let proof_data = block
+            .proof_data
+            .iter()
+            .map(|(k, v)| (k.as_bytes().to_vec(), v.clone()))
+            .collect();
+        ChainModule::author_block(
+            Origin::signed(author),
+            0,
+            *block.offchain.root(),
+            block.proof.clone(),
+            proof_data,
+        )
+    }
+
+    #[test]
+    fn test_author_block() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(ChainModule::create_chain(Origin::signed(1)));
+            assert_eq!(ChainModule::authors(0), Some(1));
+
+            let b0 = seal(0, None);
+            let b1 = seal(1, Some(*b0.offchain.root()));
+            assert_noop!(author_block(1, &b1), Error::<Test>::InvalidNumber);
+            assert_noop!(author_block(2, &b0), Error::<Test>::Unauthorized);
+            assert_ok!(author_block(1, &b0));
+            assert_ok!(author_block(1, &b1));
+            assert_eq!(ChainModule::heads(0), Some((*b1.offchain.root(), 1)));
+
+            let b2 = seal(2, Some(*b0.offchain.root()));
+            assert_noop!(author_block(1, &b2), Error::<Test>::InvalidAncestor);
+
+            let mut b2 = seal(2, Some(*b1.offchain.root()));
+            b2.proof_data[0].1 = Some(3u64.encode());
+            assert_noop!(author_block(1, &b2), Error::<Test>::InvalidProof);
+        });
+    }
+
+    #[test]
+    fn test_author_block_weight() {
+        let call = |block: &SealedBlock<TreeHasherBlake2b256>| {
+            let proof_data = block
+                .proof_data
+                .iter()
+                .map(|(k, v)| (k.as_bytes().to_vec(), v.clone()))
+                .collect();
+            Call::<Test>::author_block(0, *block.offchain.root(), block.proof.clone(), proof_data)
+        };
+        let b0 = seal(0, None);
+        let weight = call(&b0).get_dispatch_info().weight;
+        assert!(weight > AUTHOR_BLOCK_WEIGHT);
+
+        let mut b1 = seal(1, None);
+        b1.proof.push(vec![0; 1000]);
+        let weight1 = call(&b1).get_dispatch_info().weight;
+        assert!(weight1 >= weight + 1000 * PROOF_BYTE_WEIGHT);
+    }
+}
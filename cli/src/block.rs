@@ -0,0 +1,64 @@
+use clap::Clap;
+use std::convert::TryFrom;
+use sunshine_client_utils::codec::hasher::TreeHasherBlake2b256;
+use sunshine_client_utils::codec::schema::{render, SchemaRegistry};
+use sunshine_client_utils::codec::trie::{OffchainBlock, TrieError};
+use sunshine_client_utils::codec::Cid;
+use sunshine_client_utils::libipld::store::Store;
+use sunshine_client_utils::{Client, Node, Result};
+use thiserror::Error;
+
+#[derive(Clone, Debug, Clap)]
+pub struct BlockInspectCommand {
+    /// Cid of the block.
+    pub cid: String,
+
+    /// Name of the schema to decode the block with.
+    #[clap(long = "schema")]
+    pub schema: Option<String>,
+
+    /// Print yaml instead of json.
+    #[clap(long = "yaml")]
+    pub yaml: bool,
+}
+
+impl BlockInspectCommand {
+    pub async fn exec<N: Node, C: Client<N>>(
+        &self,
+        client: &C,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let cid = Cid::try_from(self.cid.as_str())?;
+        let schema = if let Some(name) = &self.schema {
+            Some(
+                registry
+                    .get(name)
+                    .ok_or_else(|| UnknownSchema(name.clone()))?,
+            )
+        } else {
+            None
+        };
+
+        let block = client.offchain_client().get(&cid).await?;
+        let offchain: OffchainBlock<TreeHasherBlake2b256> = block.decode()?;
+        if Cid::from(*offchain.root()) != cid {
+            return Err(TrieError::RootMissmatch.into());
+        }
+
+        let data = render(&offchain, schema);
+        let value = serde_json::json!({
+            "cid": cid.to_string(),
+            "data": data,
+        });
+        if self.yaml {
+            println!("{}", serde_yaml::to_string(&value)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown schema {0}")]
+pub struct UnknownSchema(pub String);
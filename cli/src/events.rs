@@ -0,0 +1,52 @@
+use async_std::prelude::*;
+use clap::Clap;
+use sunshine_client_utils::{Client, EventFilter, Node, Result};
+
+#[derive(Clone, Debug, Clap)]
+pub struct EventsWatchCommand {
+    /// Only events of the module.
+    #[clap(long = "module")]
+    pub module: Option<String>,
+
+    /// Only events with the name.
+    #[clap(long = "event")]
+    pub event: Option<String>,
+
+    /// Only events concerning the account of the signer.
+    #[clap(long = "mine")]
+    pub mine: bool,
+
+    /// Block number to start from.
+    #[clap(long = "from")]
+    pub from: Option<u32>,
+}
+
+impl EventsWatchCommand {
+    pub async fn exec<N: Node, C: Client<N>>(&self, client: &C) -> Result<()> {
+        let mut filter = EventFilter::new();
+        if let Some(module) = &self.module {
+            filter = filter.module(module);
+        }
+        if let Some(event) = &self.event {
+            filter = filter.event(event);
+        }
+        if self.mine {
+            filter = filter.account(client.signer()?.account_id().clone());
+        }
+        if let Some(from) = self.from {
+            filter = filter.resume_from(from.into());
+        }
+        let mut events = client.subscribe_events(filter);
+        while let Some(event) = events.next().await {
+            let event = event?;
+            println!(
+                "#{:?} {}::{} ({} bytes)",
+                event.block_number,
+                event.module,
+                event.variant,
+                event.data.len()
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
+pub mod block;
+pub mod events;
 pub mod key;
+pub mod node;
+pub mod offchain;
 pub mod wallet;
 pub use sunshine_client_utils as client;
 
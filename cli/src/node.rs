@@ -0,0 +1,251 @@
+use clap::Clap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use sunshine_client_utils::sc_service::{ChainSpec, Configuration, Role};
+use sunshine_client_utils::{
+    load_chain_spec, service_config, ChainOp, ClientConfig, Node, Pruning, Result, ServiceError,
+    FULL_NODE_DIR, LIGHT_CLIENT_DIR,
+};
+
+fn full_config<N: Node>(
+    root: &Path,
+    chain: Option<&Path>,
+    pruning: Pruning,
+) -> Result<Configuration> {
+    let chain_spec = load_chain_spec::<N>(chain)?;
+    let client_config = ClientConfig::load(root)?;
+    let mut config = service_config::<N>(
+        root.join(FULL_NODE_DIR),
+        chain_spec,
+        Role::Full,
+        &client_config,
+        None,
+    );
+    config.pruning = pruning.into();
+    Ok(config)
+}
+
+fn chain_op<N: Node>(root: &Path, chain: Option<&Path>, op: ChainOp) -> Result<()> {
+    let config = full_config::<N>(root, chain, Pruning::Archive)?;
+    N::chain_op(config, op).map_err(|e| ServiceError(e.to_string()))?;
+    Ok(())
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct NodeRunCommand {
+    /// Path to the chain spec, defaults to the dev chain.
+    #[clap(long = "chain")]
+    pub chain: Option<PathBuf>,
+
+    /// Runs a full node instead of a light client.
+    #[clap(long = "full")]
+    pub full: bool,
+
+    /// Keeps the state of all blocks.
+    #[clap(long = "archive", conflicts_with = "keep-blocks")]
+    pub archive: bool,
+
+    /// Number of finalized blocks to keep the state of.
+    #[clap(long = "keep-blocks")]
+    pub keep_blocks: Option<u32>,
+}
+
+impl NodeRunCommand {
+    pub async fn exec<N: Node>(&self, root: &Path) -> Result<()> {
+        let task_manager = if self.full {
+            let pruning = if self.archive {
+                Pruning::Archive
+            } else if let Some(n) = self.keep_blocks {
+                Pruning::KeepBlocks(n)
+            } else {
+                Pruning::default()
+            };
+            let config = full_config::<N>(root, self.chain.as_deref(), pruning)?;
+            N::new_full(config)
+                .map_err(|e| ServiceError(e.to_string()))?
+                .0
+        } else {
+            let chain_spec = load_chain_spec::<N>(self.chain.as_deref())?;
+            let client_config = ClientConfig::load(root)?;
+            let config = service_config::<N>(
+                root.join(LIGHT_CLIENT_DIR),
+                chain_spec,
+                Role::Light,
+                &client_config,
+                None,
+            );
+            N::new_light(config)
+                .map_err(|e| ServiceError(e.to_string()))?
+                .0
+        };
+        task_manager
+            .future()
+            .await
+            .map_err(|e| ServiceError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct PurgeChainCommand {
+    /// Skips the confirmation prompt.
+    #[clap(short = 'y')]
+    pub yes: bool,
+}
+
+impl PurgeChainCommand {
+    pub async fn exec<N: Node>(&self, root: &Path) -> Result<()> {
+        let dbs: Vec<_> = [LIGHT_CLIENT_DIR, FULL_NODE_DIR]
+            .iter()
+            .map(|dir| root.join(dir))
+            .filter(|dir| dir.exists())
+            .collect();
+        if dbs.is_empty() {
+            println!("No chain database found.");
+            return Ok(());
+        }
+        if !self.yes {
+            for db in &dbs {
+                println!("{}", db.display());
+            }
+            print!("Are you sure to remove the chain databases? [y/N]: ");
+            std::io::stdout().flush()?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            if !input.trim().eq_ignore_ascii_case("y") {
+                println!("Aborted");
+                return Ok(());
+            }
+        }
+        for db in &dbs {
+            std::fs::remove_dir_all(db)?;
+            println!("{} removed.", db.display());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct ExportBlocksCommand {
+    /// Path to the chain spec, defaults to the dev chain.
+    #[clap(long = "chain")]
+    pub chain: Option<PathBuf>,
+
+    /// File to export the blocks to.
+    pub output: PathBuf,
+
+    /// First block to export.
+    #[clap(long = "from", default_value = "1")]
+    pub from: u32,
+
+    /// Last block to export, defaults to the best block.
+    #[clap(long = "to")]
+    pub to: Option<u32>,
+
+    /// Exports the blocks in binary instead of json.
+    #[clap(long = "binary")]
+    pub binary: bool,
+}
+
+impl ExportBlocksCommand {
+    pub async fn exec<N: Node>(&self, root: &Path) -> Result<()> {
+        let op = ChainOp::ExportBlocks {
+            output: self.output.clone(),
+            from: self.from,
+            to: self.to,
+            binary: self.binary,
+        };
+        chain_op::<N>(root, self.chain.as_deref(), op)
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct ImportBlocksCommand {
+    /// Path to the chain spec, defaults to the dev chain.
+    #[clap(long = "chain")]
+    pub chain: Option<PathBuf>,
+
+    /// File to import the blocks from.
+    pub input: PathBuf,
+
+    /// Imports the blocks from binary instead of json.
+    #[clap(long = "binary")]
+    pub binary: bool,
+}
+
+impl ImportBlocksCommand {
+    pub async fn exec<N: Node>(&self, root: &Path) -> Result<()> {
+        let op = ChainOp::ImportBlocks {
+            input: self.input.clone(),
+            binary: self.binary,
+        };
+        chain_op::<N>(root, self.chain.as_deref(), op)
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct CheckBlockCommand {
+    /// Path to the chain spec, defaults to the dev chain.
+    #[clap(long = "chain")]
+    pub chain: Option<PathBuf>,
+
+    /// Block number or hex encoded block hash.
+    pub block: String,
+}
+
+impl CheckBlockCommand {
+    pub async fn exec<N: Node>(&self, root: &Path) -> Result<()> {
+        let op = ChainOp::CheckBlock {
+            block: self.block.clone(),
+        };
+        chain_op::<N>(root, self.chain.as_deref(), op)
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct BuildSpecCommand {
+    /// Path to the chain spec, defaults to the dev chain.
+    #[clap(long = "chain")]
+    pub chain: Option<PathBuf>,
+
+    /// Prints the chain spec with the genesis storage.
+    #[clap(long = "raw")]
+    pub raw: bool,
+}
+
+impl BuildSpecCommand {
+    pub async fn exec<N: Node>(&self) -> Result<()> {
+        let chain_spec = load_chain_spec::<N>(self.chain.as_deref())?;
+        let json = chain_spec.as_json(self.raw).map_err(ServiceError)?;
+        println!("{}", json);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Clap)]
+pub struct TestnetSpecCommand {
+    /// Number of keyring authorities.
+    #[clap(long = "authorities", default_value = "2")]
+    pub authorities: usize,
+
+    /// Prints the chain spec with the genesis storage.
+    #[clap(long = "raw")]
+    pub raw: bool,
+
+    /// File to write the chain spec to, defaults to stdout.
+    #[clap(long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+impl TestnetSpecCommand {
+    pub async fn exec<N: Node>(&self) -> Result<()> {
+        let chain_spec = N::chain_spec_testnet(self.authorities)?;
+        let json = chain_spec.as_json(self.raw).map_err(ServiceError)?;
+        if let Some(output) = &self.output {
+            std::fs::write(output, json)?;
+        } else {
+            println!("{}", json);
+        }
+        Ok(())
+    }
+}
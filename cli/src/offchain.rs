@@ -0,0 +1,46 @@
+use clap::Clap;
+use sunshine_client_utils::{Client, Node, Result};
+
+#[derive(Clone, Debug, Clap)]
+pub struct OffchainStatusCommand;
+
+impl OffchainStatusCommand {
+    pub async fn exec<N: Node, C: Client<N>>(&self, client: &C) -> Result<()> {
+        let stats = client.offchain_stats()?;
+        let storage = &stats.storage;
+        let network = &stats.network;
+        println!("storage");
+        if let Some(disk_size) = storage.disk_size {
+            println!("  disk size: {} bytes", disk_size);
+        }
+        println!(
+            "  inserted: {} blocks ({} bytes)",
+            storage.inserted_blocks, storage.inserted_bytes
+        );
+        if let Some(hit_rate) = storage.local_hit_rate() {
+            println!(
+                "  local lookups: {:.1}% found ({} found, {} not found)",
+                hit_rate * 100.0,
+                storage.local_hits,
+                storage.local_misses
+            );
+        }
+        println!("bitswap");
+        println!("  outstanding wants: {}", network.outstanding_wants);
+        println!(
+            "  received: {} blocks ({} bytes), {} wants",
+            network.received_blocks, network.received_bytes, network.received_wants
+        );
+        println!(
+            "  sent: {} blocks ({} bytes)",
+            network.sent_blocks, network.sent_bytes
+        );
+        println!("dht");
+        println!("  providing: {}", network.providing);
+        println!(
+            "  provider queries: {} pending, {} failed",
+            network.pending_provider_queries, network.failed_provider_queries
+        );
+        Ok(())
+    }
+}
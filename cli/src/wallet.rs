@@ -1,11 +1,11 @@
 use clap::Clap;
 use core::fmt::{Debug, Display};
-use substrate_subxt::balances::{AccountData, Balances, TransferCallExt, TransferEventExt};
+use substrate_subxt::balances::{AccountData, Balances, TransferCall, TransferEvent};
 use substrate_subxt::sp_core::crypto::Ss58Codec;
 use substrate_subxt::system::{AccountStoreExt, System};
 use substrate_subxt::{Runtime, SignedExtension, SignedExtra};
 use sunshine_client_utils::crypto::ss58::Ss58;
-use sunshine_client_utils::{Client, Node, Result};
+use sunshine_client_utils::{Client, MortalExtra, Node, Result, TxConfig, TxSubmitter};
 use thiserror::Error;
 
 #[derive(Clone, Debug, Clap)]
@@ -44,25 +44,25 @@ impl WalletTransferCommand {
         <<<N::Runtime as Runtime>::Extra as SignedExtra<N::Runtime>>::Extra as SignedExtension>::AdditionalSigned:
             Send + Sync,
         <N::Runtime as Balances>::Balance: From<u128> + Display,
+        <<N::Runtime as Runtime>::Extra as SignedExtra<N::Runtime>>::Extra: MortalExtra<N::Runtime>,
     {
         let account_id: Ss58<N::Runtime> = self.identifier.parse()?;
-        let signer = client.chain_signer()?;
-        let event = client
-            .chain_client()
-            .transfer_and_watch(&signer, &account_id.0.into(), self.amount.into())
-            .await?
-            .transfer()
-            .map_err(|_| TransferEventDecode)?
+        let to = account_id.0.into();
+        let call = TransferCall {
+            to: &to,
+            amount: self.amount.into(),
+        };
+        let outcome = TxSubmitter::new(client, TxConfig::default())
+            .submit(call, &mut |stage| println!("{}", stage))
+            .await?;
+        let event = outcome
+            .find_event::<TransferEvent<N::Runtime>>()?
             .ok_or(TransferEventFind)?;
         println!("transfered {} to {}", event.amount, event.to.to_string());
         Ok(())
     }
 }
 
-#[derive(Debug, Error)]
-#[error("Failed to decode transfer event")]
-pub struct TransferEventDecode;
-
 #[derive(Debug, Error)]
 #[error("Failed to find transfer event")]
 pub struct TransferEventFind;
@@ -1,6 +1,8 @@
 use crate::Result;
 use parity_scale_codec::{Decode, Encode};
+use sunshine_codec::schema::{Describe, Schema, TreeSchema, TypeDescriptor};
 use sunshine_codec::trie::{BlockBuilder, Hasher, OffchainBlock, TreeDecode, TreeEncode};
+use sunshine_codec::Cid;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GenericBlock<T, N, H: Hasher> {
@@ -11,11 +13,17 @@ pub struct GenericBlock<T, N, H: Hasher> {
 
 impl<T: Encode, N: Encode, H: Hasher> TreeEncode<H> for GenericBlock<T, N, H>
 where
-    H::Out: Encode + 'static,
+    H::Out: Encode + Into<Cid> + 'static,
 {
     fn encode_tree(&self, block: &mut BlockBuilder<H>, _prefix: &str, _proof: bool) {
         block.insert("number".into(), &self.number, true);
         block.insert("ancestor".into(), &self.ancestor, true);
+        // the ancestor hash is verified onchain, the cid links the history so
+        // that it's pinned and fetched with the block.
+        if let Some(ancestor) = self.ancestor {
+            let link: Cid = ancestor.into();
+            block.insert("ancestor_link".into(), &link, false);
+        }
         block.insert("payload".into(), &self.payload, false);
     }
 }
@@ -32,3 +40,15 @@ where
         })
     }
 }
+
+impl<T: Describe, N: Describe, H: Hasher> TreeSchema for GenericBlock<T, N, H> {
+    fn describe_tree(schema: &mut Schema, _prefix: &str) {
+        schema.insert("number".into(), N::describe());
+        schema.insert(
+            "ancestor".into(),
+            TypeDescriptor::Option(Box::new(TypeDescriptor::FixedBytes(H::LENGTH))),
+        );
+        schema.insert("ancestor_link".into(), TypeDescriptor::Cid);
+        schema.insert("payload".into(), T::describe());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{GenericBlock, Result};
+use libipld::block::Block;
+use libipld::store::{Store, StoreParams};
+use parity_scale_codec::{Decode, Encode};
+use std::marker::PhantomData;
+use sunshine_codec::codec::{Multicodec, TreeCodec};
+use sunshine_codec::hasher::{
+    Multihash, TreeHashBlake2b256, TreeHasherBlake2b256, BLAKE2B_256_TREE,
+};
+use sunshine_codec::trie::{OffchainBlock, SealedBlock, TreeDecode, TreeEncode};
+use sunshine_codec::Cid;
+use thiserror::Error;
+
+/// Block of an offchain chain.
+pub type ChainBlock<T> = GenericBlock<T, u64, TreeHasherBlake2b256>;
+
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("block root doesn't match cid")]
+    RootMissmatch,
+    #[error("expected block number {expected} but found {found}")]
+    NumberMissmatch { expected: u64, found: u64 },
+    #[error("genesis block must have number 0")]
+    InvalidGenesis,
+    #[error("block number 0 must not have an ancestor")]
+    UnexpectedAncestor,
+}
+
+/// Relation between two chain heads.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeadRelation {
+    /// Both heads are the same block.
+    Same,
+    /// The first head is an ancestor of the second head.
+    Ancestor,
+    /// The first head is a descendant of the second head.
+    Descendant,
+    /// The heads share a common ancestor but diverge after it.
+    Fork(Cid),
+    /// The heads don't share a genesis block.
+    Unrelated,
+}
+
+/// A chain of `GenericBlock`s stored in an offchain store.
+pub struct OffchainChain<S, T> {
+    _marker: PhantomData<T>,
+    store: S,
+    head: Option<(TreeHashBlake2b256, u64)>,
+}
+
+impl<S, T> OffchainChain<S, T>
+where
+    S: Store,
+    S::Params: StoreParams<Codecs = Multicodec, Hashes = Multihash>,
+    T: Encode + Decode + Send + Sync,
+{
+    /// Creates an empty chain.
+    pub fn new(store: S) -> Self {
+        Self {
+            _marker: PhantomData,
+            store,
+            head: None,
+        }
+    }
+
+    /// Opens a chain at `head`.
+    pub async fn open(store: S, head: &Cid) -> Result<Self> {
+        let mut chain = Self::new(store);
+        let (offchain, block) = chain.fetch(head).await?;
+        chain.head = Some((*offchain.root(), block.number));
+        Ok(chain)
+    }
+
+    /// Returns the cid of the head.
+    pub fn head(&self) -> Option<Cid> {
+        self.head.map(|(root, _)| Cid::from(root))
+    }
+
+    /// Returns the number of the head.
+    pub fn number(&self) -> Option<u64> {
+        self.head.map(|(_, number)| number)
+    }
+
+    /// Appends a payload to the chain and returns the sealed block.
+    pub async fn append(&mut self, payload: T) -> Result<SealedBlock<TreeHasherBlake2b256>> {
+        let (number, ancestor) = match self.head {
+            Some((root, number)) => (number + 1, Some(root)),
+            None => (0, None),
+        };
+        let block = ChainBlock {
+            number,
+            ancestor,
+            payload,
+        };
+        let sealed = block.seal()?;
+        let ipld = Block::<S::Params>::encode(TreeCodec, BLAKE2B_256_TREE, &sealed.offchain)?;
+        self.store.insert(&ipld).await?;
+        self.head = Some((*sealed.offchain.root(), number));
+        Ok(sealed)
+    }
+
+    /// Fetches a block and checks that its root matches the cid.
+    pub async fn get(&self, cid: &Cid) -> Result<ChainBlock<T>> {
+        Ok(self.fetch(cid).await?.1)
+    }
+
+    async fn fetch(
+        &self,
+        cid: &Cid,
+    ) -> Result<(OffchainBlock<TreeHasherBlake2b256>, ChainBlock<T>)> {
+        let block = self.store.get(cid).await?;
+        let offchain: OffchainBlock<TreeHasherBlake2b256> = block.decode()?;
+        if Cid::from(*offchain.root()) != *cid {
+            return Err(ChainError::RootMissmatch.into());
+        }
+        let block = TreeDecode::decode(&offchain)?;
+        Ok((offchain, block))
+    }
+
+    /// Iterates the history starting at `head` and ending at the genesis block.
+    pub fn iter(&self, head: Cid) -> ChainIter<'_, S, T> {
+        ChainIter {
+            chain: self,
+            next: Some(head),
+        }
+    }
+
+    /// Verifies the history starting at `head` and returns its length.
+    ///
+    /// Every ancestor is fetched by the cid derived from the ancestor hash and
+    /// its root is checked against it, so this verifies the ancestor hashes as
+    /// well as the continuity of the block numbers.
+    pub async fn verify(&self, head: &Cid) -> Result<u64> {
+        let mut iter = self.iter(*head);
+        let mut expected = None;
+        let mut len = 0;
+        while let Some((_, block)) = iter.next().await? {
+            if let Some(expected) = expected {
+                if block.number != expected {
+                    return Err(ChainError::NumberMissmatch {
+                        expected,
+                        found: block.number,
+                    }
+                    .into());
+                }
+            }
+            match (block.number, block.ancestor.is_some()) {
+                (0, true) => return Err(ChainError::UnexpectedAncestor.into()),
+                (_, false) if block.number != 0 => return Err(ChainError::InvalidGenesis.into()),
+                _ => {}
+            }
+            expected = block.number.checked_sub(1);
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Determines how two heads relate to each other.
+    pub async fn relation(&self, a: &Cid, b: &Cid) -> Result<HeadRelation> {
+        if a == b {
+            return Ok(HeadRelation::Same);
+        }
+        let mut a = (*a, self.get(a).await?);
+        let mut b = (*b, self.get(b).await?);
+        let a_number = a.1.number;
+        let b_number = b.1.number;
+        while a.1.number > b.1.number {
+            a = match self.parent(&a.1).await? {
+                Some(parent) => parent,
+                None => return Err(ChainError::InvalidGenesis.into()),
+            };
+        }
+        while b.1.number > a.1.number {
+            b = match self.parent(&b.1).await? {
+                Some(parent) => parent,
+                None => return Err(ChainError::InvalidGenesis.into()),
+            };
+        }
+        if a.0 == b.0 {
+            return Ok(if a_number < b_number {
+                HeadRelation::Ancestor
+            } else {
+                HeadRelation::Descendant
+            });
+        }
+        loop {
+            match (self.parent(&a.1).await?, self.parent(&b.1).await?) {
+                (Some(pa), Some(pb)) if pa.0 == pb.0 => return Ok(HeadRelation::Fork(pa.0)),
+                (Some(pa), Some(pb)) => {
+                    a = pa;
+                    b = pb;
+                }
+                _ => return Ok(HeadRelation::Unrelated),
+            }
+        }
+    }
+
+    async fn parent(&self, block: &ChainBlock<T>) -> Result<Option<(Cid, ChainBlock<T>)>> {
+        if let Some(ancestor) = block.ancestor {
+            let cid = Cid::from(ancestor);
+            let block = self.get(&cid).await?;
+            Ok(Some((cid, block)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Iterator over the history of an `OffchainChain`.
+pub struct ChainIter<'a, S, T> {
+    chain: &'a OffchainChain<S, T>,
+    next: Option<Cid>,
+}
+
+impl<'a, S, T> ChainIter<'a, S, T>
+where
+    S: Store,
+    S::Params: StoreParams<Codecs = Multicodec, Hashes = Multihash>,
+    T: Encode + Decode + Send + Sync,
+{
+    /// Returns the next block or `None` after the genesis block.
+    pub async fn next(&mut self) -> Result<Option<(Cid, ChainBlock<T>)>> {
+        let cid = if let Some(cid) = self.next.take() {
+            cid
+        } else {
+            return Ok(None);
+        };
+        let block = self.chain.get(&cid).await?;
+        self.next = block.ancestor.map(Cid::from);
+        Ok(Some((cid, block)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::mem::MemStore;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl StoreParams for Params {
+        type Hashes = Multihash;
+        type Codecs = Multicodec;
+        const MAX_BLOCK_SIZE: usize = u16::MAX as _;
+    }
+
+    #[async_std::test]
+    async fn test_chain() {
+        let store = MemStore::<Params>::default();
+        let mut chain = OffchainChain::<_, String>::new(store.clone());
+        chain.append("genesis".into()).await.unwrap();
+        chain.append("one".into()).await.unwrap();
+        let one = chain.head().unwrap();
+        chain.append("two".into()).await.unwrap();
+        let two = chain.head().unwrap();
+        assert_eq!(chain.number(), Some(2));
+        assert_eq!(chain.verify(&two).await.unwrap(), 3);
+
+        let mut iter = chain.iter(two);
+        let mut payloads = vec![];
+        while let Some((_, block)) = iter.next().await.unwrap() {
+            payloads.push(block.payload);
+        }
+        assert_eq!(payloads, vec!["two", "one", "genesis"]);
+
+        let mut fork = OffchainChain::<_, String>::open(store, &one).await.unwrap();
+        fork.append("fork".into()).await.unwrap();
+        let fork = fork.head().unwrap();
+
+        assert_eq!(
+            chain.relation(&two, &two).await.unwrap(),
+            HeadRelation::Same
+        );
+        assert_eq!(
+            chain.relation(&one, &two).await.unwrap(),
+            HeadRelation::Ancestor
+        );
+        assert_eq!(
+            chain.relation(&two, &one).await.unwrap(),
+            HeadRelation::Descendant
+        );
+        assert_eq!(
+            chain.relation(&two, &fork).await.unwrap(),
+            HeadRelation::Fork(one)
+        );
+    }
+}
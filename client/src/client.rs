@@ -1,10 +1,19 @@
-use crate::{Client, Network, Node, OffchainClient, OffchainConfig, OffchainStore};
+use crate::{
+    node_key_config, Client, ClientConfig, ClientMetrics, Connect, DeviceRegistry, EventFilter,
+    EventStream, Network, Node, OffchainClient, OffchainConfig, OffchainMonitor, OffchainNetwork,
+    OffchainRpcStore, OffchainStats, OffchainStore, OffchainStoreHandle, Pins, Pruning, TxJournal,
+    FULL_NODE_DIR, LIGHT_CLIENT_DIR,
+};
 use anyhow::Result;
+use async_std::task;
 use async_trait::async_trait;
+use futures::future::FutureExt;
+use sc_network::config::identity::Keypair;
 use sp_core::Pair;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 use std::convert::TryInto;
 use std::path::Path;
+use std::sync::Arc;
 use substrate_subxt::{
     sp_core, sp_runtime, system::System, ClientBuilder, Runtime, SignedExtension, SignedExtra,
 };
@@ -15,12 +24,36 @@ use sunshine_crypto::signer::{GenericSigner, GenericSubxtSigner, Signer};
 use sunshine_keystore::Keystore as KeybaseKeystore;
 
 pub struct GenericClient<N: Node, K: KeyType, O: Send + Sync> {
-    network: Network<N>,
+    network: Option<Network<N>>,
+    network_key: Option<Keypair>,
     keystore: KeybaseKeystore<K>,
     keychain: KeyChain,
     signer: Option<GenericSigner<N::Runtime, K>>,
     chain_client: substrate_subxt::Client<N::Runtime>,
+    rpc_client: jsonrpsee::Client,
+    tx_journal: TxJournal,
     offchain_client: O,
+    pins: Pins<OffchainStore<N>>,
+    offchain_monitor: OffchainMonitor<N>,
+    devices: DeviceRegistry<N::Runtime, K>,
+    url: Option<String>,
+    metrics: ClientMetrics,
+}
+
+/// Connects to the websocket or http rpc of a remote node.
+async fn connect_remote<T: Runtime>(
+    url: &str,
+) -> Result<(jsonrpsee::Client, substrate_subxt::Client<T>)> {
+    let rpc_client = if url.starts_with("ws://") || url.starts_with("wss://") {
+        jsonrpsee::ws_client(url).await?
+    } else {
+        jsonrpsee::http_client(url)
+    };
+    let chain_client = ClientBuilder::new()
+        .set_client(rpc_client.clone())
+        .build()
+        .await?;
+    Ok((rpc_client, chain_client))
 }
 
 #[async_trait]
@@ -43,8 +76,12 @@ where
     type Keystore = KeybaseKeystore<K>;
     type OffchainClient = O;
 
-    fn network(&self) -> &Network<N> {
-        &self.network
+    fn try_network(&self) -> Option<&Network<N>> {
+        self.network.as_ref()
+    }
+
+    fn network_key(&self) -> Option<&Keypair> {
+        self.network_key.as_ref()
     }
 
     fn keystore(&self) -> &Self::Keystore {
@@ -98,7 +135,13 @@ where
     }
 
     async fn unlock(&mut self, password: &SecretString) -> Result<()> {
-        let key = self.keystore.unlock(password).await?;
+        let key = match self.keystore.unlock(password).await {
+            Ok(key) => key,
+            Err(err) => {
+                self.metrics.keystore_unlock_failures.inc();
+                return Err(err);
+            }
+        };
         self.keychain.insert(key.clone());
         self.signer = Some(GenericSigner::new(key));
         Ok(())
@@ -108,9 +151,50 @@ where
         &self.chain_client
     }
 
+    fn rpc_client(&self) -> &jsonrpsee::Client {
+        &self.rpc_client
+    }
+
+    fn tx_journal(&self) -> &TxJournal {
+        &self.tx_journal
+    }
+
     fn offchain_client(&self) -> &Self::OffchainClient {
         &self.offchain_client
     }
+
+    fn pins(&self) -> &Pins<OffchainStore<N>> {
+        &self.pins
+    }
+
+    fn offchain_stats(&self) -> Result<OffchainStats> {
+        self.offchain_monitor.stats()
+    }
+
+    fn offchain_network(&self) -> &OffchainNetwork<N> {
+        self.offchain_monitor.network()
+    }
+
+    fn devices(&self) -> &DeviceRegistry<N::Runtime, K> {
+        &self.devices
+    }
+
+    /// Subscribes to the runtime events of finalized blocks.
+    ///
+    /// Clients connected to a remote node reconnect to it when the
+    /// subscription fails.
+    fn subscribe_events(&self, filter: EventFilter<N::Runtime>) -> EventStream<N::Runtime> {
+        let url = if let Some(url) = &self.url {
+            url.clone()
+        } else {
+            return EventStream::new(self.chain_client.clone(), filter);
+        };
+        let connect: Connect<N::Runtime> = Box::new(move || {
+            let url = url.clone();
+            async move { Ok(connect_remote(&url).await?.1) }.boxed()
+        });
+        EventStream::with_connect(self.chain_client.clone(), filter, connect)
+    }
 }
 
 impl<N, K, O> GenericClient<N, K, O>
@@ -128,19 +212,133 @@ where
     <K::Pair as Pair>::Signature: Into<<N::Runtime as Runtime>::Signature>,
     O: OffchainClient<OffchainStore<N>>,
 {
+    /// Creates a client running a light node.
+    ///
+    /// The network is configured from the config file in the root directory.
     pub async fn new(
         root: &Path,
         chain_spec: &Path,
     ) -> Result<Self> {
-        let (client, network) = N::new(root.join("light-client"), chain_spec)?;
+        let config = ClientConfig::load(root)?;
+        Self::new_with_config(root, chain_spec, &config).await
+    }
+
+    /// Creates a client running a light node with a network config.
+    pub async fn new_with_config(
+        root: &Path,
+        chain_spec: &Path,
+        config: &ClientConfig,
+    ) -> Result<Self> {
+        let path = root.join(LIGHT_CLIENT_DIR);
+        let metrics = ClientMetrics::new();
+        let offchain = OffchainStoreHandle::default();
+        let (client, network) = N::new(
+            path.clone(),
+            chain_spec,
+            config,
+            metrics.registry(),
+            offchain.clone(),
+        )?;
+        let network_key = node_key_config(&path).into_keypair()?;
+        Self::from_node(root, client, network, network_key, offchain, metrics).await
+    }
+
+    /// Creates a client connected to the websocket or http rpc of a remote
+    /// node.
+    ///
+    /// The keystore stays local. The offchain store doesn't exchange blocks
+    /// with peers, so only locally inserted blocks are available. Failing to
+    /// serve the metrics is logged.
+    pub async fn new_remote(root: &Path, url: &str) -> Result<Self> {
+        let (rpc_client, chain_client) = connect_remote(url).await?;
+        let metrics = ClientMetrics::new();
+        if let Some(addr) = ClientConfig::load(root)?.prometheus_addr() {
+            let server = metrics.serve(addr);
+            task::spawn(async move {
+                if let Err(err) = server.await {
+                    log::error!("failed to serve metrics on {}: {}", addr, err);
+                }
+            });
+        }
+        let (store, offchain_monitor) = OffchainConfig::offline()
+            .path(root.join("ipfs-embed"))
+            .metrics(metrics)
+            .build()?;
+        let mut client =
+            Self::from_parts(root, None, None, chain_client, rpc_client, store, offchain_monitor)
+                .await?;
+        client.url = Some(url.to_string());
+        Ok(client)
+    }
+
+    /// Creates a client running a full node.
+    ///
+    /// A full node stores the state according to the pruning mode and serves
+    /// light clients, but doesn't author blocks.
+    pub async fn new_full(
+        root: &Path,
+        chain_spec: &Path,
+        pruning: Pruning,
+    ) -> Result<Self> {
+        let config = ClientConfig::load(root)?;
+        let path = root.join(FULL_NODE_DIR);
+        let metrics = ClientMetrics::new();
+        let offchain = OffchainStoreHandle::default();
+        let (client, network) = N::new_full_node(
+            path.clone(),
+            chain_spec,
+            &config,
+            metrics.registry(),
+            pruning,
+            offchain.clone(),
+        )?;
+        let network_key = node_key_config(&path).into_keypair()?;
+        Self::from_node(root, client, network, network_key, offchain, metrics).await
+    }
+
+    /// Creates a client for a node and serves its offchain store over the
+    /// rpc of the node.
+    async fn from_node(
+        root: &Path,
+        client: jsonrpsee::Client,
+        network: Network<N>,
+        network_key: Keypair,
+        offchain: OffchainStoreHandle,
+        metrics: ClientMetrics,
+    ) -> Result<Self> {
         let chain_client = ClientBuilder::new()
-            .set_client(client)
+            .set_client(client.clone())
             .build()
             .await?;
-
-        let store = OffchainConfig::new(network.clone())
+        let (store, offchain_monitor) = OffchainConfig::new(network.clone())
             .path(root.join("ipfs-embed"))
+            .metrics(metrics)
             .build()?;
+        offchain.set(Arc::new(OffchainRpcStore::new(store.clone())));
+        Self::from_parts(
+            root,
+            Some(network),
+            Some(network_key),
+            chain_client,
+            client,
+            store,
+            offchain_monitor,
+        )
+        .await
+    }
+
+    async fn from_parts(
+        root: &Path,
+        network: Option<Network<N>>,
+        network_key: Option<Keypair>,
+        chain_client: substrate_subxt::Client<N::Runtime>,
+        rpc_client: jsonrpsee::Client,
+        store: OffchainStore<N>,
+        offchain_monitor: OffchainMonitor<N>,
+    ) -> Result<Self> {
+        let metrics = offchain_monitor.metrics().clone();
+        let tx_journal = TxJournal::open(Some(root.join("tx-journal")))?;
+        let pins = Pins::new(store.clone(), Some(root.join("ipfs-embed")));
         let offchain_client = O::from(store);
 
         let keystore = KeybaseKeystore::<K>::new(root.join("keystore"));
@@ -162,42 +360,97 @@ where
 
         Ok(Self {
             network,
+            network_key,
             keystore,
             keychain,
             signer,
             chain_client,
+            rpc_client,
+            tx_journal,
             offchain_client,
+            pins,
+            offchain_monitor,
+            devices: Default::default(),
+            url: None,
+            metrics,
         })
     }
 
+    /// Returns the prometheus metrics of the client.
+    pub fn metrics(&self) -> &ClientMetrics {
+        &self.metrics
+    }
+
     #[cfg(feature = "mock")]
     pub async fn mock(
         test_node: &crate::MockNode<N>,
         account: sp_keyring::AccountKeyring,
     ) -> (Self, tempdir::TempDir) {
-        let network = test_node.network.clone();
+        Self::mock_with(
+            test_node.client.clone(),
+            test_node.network.clone(),
+            test_node.network_key.clone(),
+            test_node.offchain.clone(),
+            account,
+        )
+        .await
+    }
+
+    /// Creates a client connected to a node of the testnet.
+    #[cfg(feature = "mock")]
+    pub async fn mock_testnet(
+        testnet: &crate::Testnet<N>,
+        node: usize,
+        account: sp_keyring::AccountKeyring,
+    ) -> (Self, tempdir::TempDir) {
+        let client = testnet.client(node).unwrap().clone();
+        let network = testnet.network(node).unwrap().clone();
+        let network_key = testnet.node(node).network_key.clone();
+        let offchain = testnet.node(node).offchain.clone();
+        Self::mock_with(client, network, network_key, offchain, account).await
+    }
+
+    #[cfg(feature = "mock")]
+    async fn mock_with(
+        rpc_client: jsonrpsee::Client,
+        network: Network<N>,
+        network_key: Keypair,
+        offchain: OffchainStoreHandle,
+        account: sp_keyring::AccountKeyring,
+    ) -> (Self, tempdir::TempDir) {
         let chain_client = ClientBuilder::new()
-            .set_client(test_node.client.clone())
+            .set_client(rpc_client.clone())
             .build()
             .await
             .unwrap();
 
-        let store = OffchainConfig::new(network.clone())
+        let (store, offchain_monitor) = OffchainConfig::new(network.clone())
             .temporary(true)
             .build()
             .unwrap();
+        let metrics = offchain_monitor.metrics().clone();
+        offchain.set(Arc::new(OffchainRpcStore::new(store.clone())));
+        let pins = Pins::new(store.clone(), None);
         let offchain_client = O::from(store);
 
         let tmp = tempdir::TempDir::new("sunshine-keystore-").unwrap();
         let keystore = KeybaseKeystore::<K>::new(tmp.path());
 
         let mut me = Self {
-            network,
+            network: Some(network),
+            network_key: Some(network_key),
             keystore,
             keychain: KeyChain::new(),
             signer: None,
             chain_client,
+            rpc_client,
+            tx_journal: TxJournal::open(None).unwrap(),
             offchain_client,
+            pins,
+            offchain_monitor,
+            devices: Default::default(),
+            url: None,
+            metrics,
         };
         let key = TypedPair::from_suri(&account.to_seed()).unwrap();
         let password = SecretString::new("password".to_string());
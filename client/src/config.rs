@@ -0,0 +1,194 @@
+use crate::Result;
+use sc_network::config::{MultiaddrWithPeerId, NonReservedPeerMode, TransportConfig};
+use sc_network::Multiaddr;
+use sc_service::config::PrometheusConfig;
+use sc_service::Configuration;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::Path;
+use substrate_prometheus_endpoint::Registry;
+
+/// Name of the config file in the root directory of the client.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Network configuration of the node.
+///
+/// Unset fields of the toml file fall back to the defaults of the node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ClientConfig {
+    /// Name of the node, reported to telemetry.
+    pub node_name: Option<String>,
+    /// Addresses to listen on.
+    pub listen_addresses: Vec<Multiaddr>,
+    /// Addresses the node is reachable at.
+    pub public_addresses: Vec<Multiaddr>,
+    /// Peers to bootstrap from, in addition to the ones in the chain spec.
+    pub boot_nodes: Vec<MultiaddrWithPeerId>,
+    /// Only connects to reserved peers.
+    pub reserved_only: bool,
+    /// Discovers peers on the local network.
+    pub mdns: bool,
+    /// Reports to the telemetry server.
+    pub telemetry: bool,
+    /// Serves prometheus metrics on localhost at this port.
+    pub prometheus_port: Option<u16>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            node_name: None,
+            listen_addresses: Default::default(),
+            public_addresses: Default::default(),
+            boot_nodes: Default::default(),
+            reserved_only: false,
+            mdns: true,
+            telemetry: true,
+            prometheus_port: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a toml config file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let toml = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&toml)?)
+    }
+
+    /// Loads the config file in the root directory of the client.
+    ///
+    /// Returns the default config if the file doesn't exist.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(CONFIG_FILE);
+        if path.exists() {
+            Self::from_file(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Writes the config file to the root directory of the client.
+    pub fn save(&self, root: &Path) -> Result<()> {
+        std::fs::create_dir_all(root)?;
+        std::fs::write(root.join(CONFIG_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn node_name(mut self, name: &str) -> Self {
+        self.node_name = Some(name.to_string());
+        self
+    }
+
+    pub fn listen_address(mut self, addr: Multiaddr) -> Self {
+        self.listen_addresses.push(addr);
+        self
+    }
+
+    pub fn public_address(mut self, addr: Multiaddr) -> Self {
+        self.public_addresses.push(addr);
+        self
+    }
+
+    pub fn boot_node(mut self, addr: MultiaddrWithPeerId) -> Self {
+        self.boot_nodes.push(addr);
+        self
+    }
+
+    pub fn reserved_only(mut self, reserved_only: bool) -> Self {
+        self.reserved_only = reserved_only;
+        self
+    }
+
+    pub fn mdns(mut self, mdns: bool) -> Self {
+        self.mdns = mdns;
+        self
+    }
+
+    pub fn telemetry(mut self, telemetry: bool) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    pub fn prometheus_port(mut self, port: u16) -> Self {
+        self.prometheus_port = Some(port);
+        self
+    }
+
+    /// Address of the prometheus exporter, if it is enabled.
+    pub(crate) fn prometheus_addr(&self) -> Option<SocketAddr> {
+        self.prometheus_port
+            .map(|port| SocketAddr::from(([127, 0, 0, 1], port)))
+    }
+
+    /// Port of the telemetry server, if telemetry is enabled.
+    pub(crate) fn telemetry_port(&self) -> Option<u16> {
+        if self.telemetry {
+            Some(8000)
+        } else {
+            None
+        }
+    }
+
+    /// Applies the config to the service configuration.
+    ///
+    /// The node service exports its metrics on `registry`, or on a new
+    /// registry if it's `None`.
+    pub(crate) fn apply(&self, config: &mut Configuration, registry: Option<&Registry>) {
+        let network = &mut config.network;
+        if let Some(name) = &self.node_name {
+            network.node_name = name.clone();
+        }
+        if !self.listen_addresses.is_empty() {
+            network.listen_addresses = self.listen_addresses.clone();
+        }
+        network.public_addresses = self.public_addresses.clone();
+        network.boot_nodes.extend(self.boot_nodes.iter().cloned());
+        if self.reserved_only {
+            network.non_reserved_mode = NonReservedPeerMode::Deny;
+        }
+        if let TransportConfig::Normal { enable_mdns, .. } = &mut network.transport {
+            *enable_mdns = self.mdns;
+        }
+        if let Some(port) = self.prometheus_addr() {
+            config.prometheus_config = Some(match registry {
+                Some(registry) => PrometheusConfig {
+                    port,
+                    registry: registry.clone(),
+                },
+                None => PrometheusConfig::new_with_default_registry(port),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_toml() {
+        let config = ClientConfig::new()
+            .node_name("sunshine")
+            .listen_address("/ip4/0.0.0.0/tcp/30333".parse().unwrap())
+            .boot_node(
+                "/ip4/127.0.0.1/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV"
+                    .parse()
+                    .unwrap(),
+            )
+            .reserved_only(true)
+            .mdns(false)
+            .prometheus_port(9615);
+        let toml = toml::to_string(&config).unwrap();
+        let config2: ClientConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(config, config2);
+
+        let config3: ClientConfig = toml::from_str("telemetry = false").unwrap();
+        assert_eq!(config3, ClientConfig::new().telemetry(false));
+    }
+}
@@ -0,0 +1,401 @@
+use crate::{Client, Node, OffchainConfig, Result};
+use ipfs_embed_core::Network;
+use libipld::block::Block;
+use libipld::store::Store;
+use parity_scale_codec::{Decode, Encode};
+use sc_network::config::identity::{Keypair, PublicKey};
+use sc_network::PeerId;
+use sp_core::hashing::blake2_256;
+use sp_core::Pair;
+use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use substrate_subxt::system::System;
+use substrate_subxt::{sp_core, sp_runtime, Runtime};
+use sunshine_codec::codec::TreeCodec;
+use sunshine_codec::hasher::{TreeHasherBlake2b256, BLAKE2B_256_TREE};
+use sunshine_codec::trie::{BlockBuilder, OffchainBlock, TreeDecode, TreeEncode};
+use sunshine_codec::Cid;
+use sunshine_crypto::array::CryptoArray;
+use sunshine_crypto::keychain::{KeyType, TypedPair, TypedPublic};
+use sunshine_crypto::signer::Signer;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DeviceCertificateError {
+    #[error("invalid peer id")]
+    InvalidPeerId,
+    #[error("peer key doesn't match the peer id")]
+    InvalidPeerKey,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("invalid device signature")]
+    InvalidDeviceSignature,
+    #[error("invalid peer signature")]
+    InvalidPeerSignature,
+    #[error("no account key")]
+    NoAccountKey,
+    #[error("no network key")]
+    NoNetworkKey,
+}
+
+/// Certificate binding a peer id and a device key to an account.
+///
+/// The certificate is signed by the network key of the peer, proving that
+/// the peer claims the device, by the device key, proving that the device
+/// claims the peer, and by the account, vouching that the device belongs to
+/// it.
+pub struct DeviceCertificate<T: Runtime, K: KeyType> {
+    pub peer_id: PeerId,
+    /// Protobuf encoded public network key of the peer.
+    pub peer_key: Vec<u8>,
+    pub device: TypedPublic<K>,
+    pub account_id: <T as System>::AccountId,
+    pub peer_signature: Vec<u8>,
+    pub device_signature: Vec<u8>,
+    pub signature: T::Signature,
+}
+
+impl<T: Runtime, K: KeyType> Clone for DeviceCertificate<T, K> {
+    fn clone(&self) -> Self {
+        Self {
+            peer_id: self.peer_id.clone(),
+            peer_key: self.peer_key.clone(),
+            device: self.device.clone(),
+            account_id: self.account_id.clone(),
+            peer_signature: self.peer_signature.clone(),
+            device_signature: self.device_signature.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl<T: Runtime, K: KeyType> std::fmt::Debug for DeviceCertificate<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("DeviceCertificate")
+            .field("peer_id", &self.peer_id)
+            .field("account_id", &self.account_id)
+            .finish()
+    }
+}
+
+impl<T, K> DeviceCertificate<T, K>
+where
+    T: Runtime,
+    T::Signature: Encode + Decode,
+    <T::Signature as Verify>::Signer: IdentifyAccount<AccountId = <T as System>::AccountId>,
+    K: KeyType,
+{
+    fn payload(
+        peer_id: &PeerId,
+        device: &TypedPublic<K>,
+        account_id: &<T as System>::AccountId,
+    ) -> Vec<u8> {
+        (
+            b"sunshine-device-certificate",
+            peer_id.as_bytes(),
+            device,
+            account_id,
+        )
+            .encode()
+    }
+
+    /// Creates a certificate for the peer of `network_key` signed by
+    /// `network_key`, `device` and `signer`.
+    pub fn new(
+        signer: &dyn Signer<T>,
+        device: &TypedPair<K>,
+        network_key: &Keypair,
+    ) -> Result<Self> {
+        let peer_key = network_key.public();
+        let peer_id = peer_key.clone().into_peer_id();
+        let account_id = signer.account_id().clone();
+        let public = device.public();
+        let payload = Self::payload(&peer_id, &public, &account_id);
+        let peer_signature = network_key.sign(&payload)?;
+        let device_signature = device.sign(&payload).as_ref().to_vec();
+        let signature = signer.sign(&payload);
+        Ok(Self {
+            peer_id,
+            peer_key: peer_key.into_protobuf_encoding(),
+            device: public,
+            account_id,
+            peer_signature,
+            device_signature,
+            signature,
+        })
+    }
+
+    /// Verifies the signatures of the peer, the device and the account.
+    pub fn verify(&self) -> Result<()> {
+        let payload = Self::payload(&self.peer_id, &self.device, &self.account_id);
+        let peer_key = PublicKey::from_protobuf_encoding(&self.peer_key)
+            .map_err(|_| DeviceCertificateError::InvalidPeerKey)?;
+        if PeerId::from_public_key(peer_key.clone()) != self.peer_id {
+            return Err(DeviceCertificateError::InvalidPeerKey.into());
+        }
+        if !peer_key.verify(&payload, &self.peer_signature) {
+            return Err(DeviceCertificateError::InvalidPeerSignature.into());
+        }
+        if !K::Pair::verify_weak(&self.device_signature, &payload, &*self.device) {
+            return Err(DeviceCertificateError::InvalidDeviceSignature.into());
+        }
+        if !self.signature.verify(&payload[..], &self.account_id) {
+            return Err(DeviceCertificateError::InvalidSignature.into());
+        }
+        Ok(())
+    }
+}
+
+impl<T, K> TreeEncode<TreeHasherBlake2b256> for DeviceCertificate<T, K>
+where
+    T: Runtime,
+    T::Signature: Encode,
+    K: KeyType,
+{
+    fn encode_tree(
+        &self,
+        block: &mut BlockBuilder<TreeHasherBlake2b256>,
+        _prefix: &str,
+        _proof: bool,
+    ) {
+        block.insert("peer_id".into(), &self.peer_id.as_bytes(), false);
+        block.insert("peer_key".into(), &self.peer_key, false);
+        block.insert("device".into(), &self.device, false);
+        block.insert("account_id".into(), &self.account_id, false);
+        block.insert("peer_signature".into(), &self.peer_signature, false);
+        block.insert("device_signature".into(), &self.device_signature, false);
+        block.insert("signature".into(), &self.signature, false);
+    }
+}
+
+impl<T, K> TreeDecode<TreeHasherBlake2b256> for DeviceCertificate<T, K>
+where
+    T: Runtime,
+    T::Signature: Decode,
+    K: KeyType,
+{
+    fn decode_tree(block: &OffchainBlock<TreeHasherBlake2b256>, _prefix: &str) -> Result<Self> {
+        let peer_id: Vec<u8> = block.get("peer_id")?;
+        Ok(Self {
+            peer_id: PeerId::from_bytes(peer_id)
+                .map_err(|_| DeviceCertificateError::InvalidPeerId)?,
+            peer_key: block.get("peer_key")?,
+            device: block.get("device")?,
+            account_id: block.get("account_id")?,
+            peer_signature: block.get("peer_signature")?,
+            device_signature: block.get("device_signature")?,
+            signature: block.get("signature")?,
+        })
+    }
+}
+
+/// Verified device certificates.
+pub struct DeviceRegistry<T: Runtime, K: KeyType> {
+    certificates: Mutex<HashMap<PeerId, DeviceCertificate<T, K>>>,
+}
+
+impl<T: Runtime, K: KeyType> Default for DeviceRegistry<T, K> {
+    fn default() -> Self {
+        Self {
+            certificates: Default::default(),
+        }
+    }
+}
+
+impl<T, K> DeviceRegistry<T, K>
+where
+    T: Runtime,
+    T::Signature: Encode + Decode,
+    <T::Signature as Verify>::Signer: IdentifyAccount<AccountId = <T as System>::AccountId>,
+    K: KeyType,
+{
+    /// Verifies and adds a certificate.
+    ///
+    /// Only the peer can sign a certificate for its peer id, so a newer
+    /// certificate for the same peer replaces the previous one.
+    pub fn insert(&self, cert: DeviceCertificate<T, K>) -> Result<()> {
+        cert.verify()?;
+        self.certificates
+            .lock()
+            .unwrap()
+            .insert(cert.peer_id.clone(), cert);
+        Ok(())
+    }
+
+    /// Removes the certificate of a peer.
+    pub fn remove(&self, peer_id: &PeerId) {
+        self.certificates.lock().unwrap().remove(peer_id);
+    }
+
+    /// Returns the account a peer belongs to.
+    pub fn account(&self, peer_id: &PeerId) -> Option<<T as System>::AccountId> {
+        self.certificates
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .map(|cert| cert.account_id.clone())
+    }
+
+    /// Returns the device key of a peer.
+    pub fn device(&self, peer_id: &PeerId) -> Option<TypedPublic<K>> {
+        self.certificates
+            .lock()
+            .unwrap()
+            .get(peer_id)
+            .map(|cert| cert.device.clone())
+    }
+
+    /// Returns the peers of an account.
+    pub fn peers(&self, account_id: &<T as System>::AccountId) -> HashSet<PeerId> {
+        self.certificates
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|cert| &cert.account_id == account_id)
+            .map(|cert| cert.peer_id.clone())
+            .collect()
+    }
+}
+
+/// Derives the device key of a peer from the key of an account.
+///
+/// Every peer of an account gets its own device key, which is distinct from
+/// the account key.
+pub fn derive_device_key<K: KeyType>(account: &TypedPair<K>, peer_id: &PeerId) -> TypedPair<K> {
+    let seed = blake2_256(
+        &(
+            b"sunshine-device-key",
+            account.seed().as_ref(),
+            peer_id.as_bytes(),
+        )
+            .encode(),
+    );
+    TypedPair::from_seed(CryptoArray::from_slice(&seed).expect("seed has valid length; qed"))
+}
+
+/// Creates a certificate for the local peer and publishes it as an offchain
+/// block.
+///
+/// The certificate is signed with the network key of the client and a
+/// device key derived from the account key.
+pub async fn publish_device_certificate<N, C>(client: &C) -> Result<Cid>
+where
+    N: Node,
+    C: Client<N>,
+    <N::Runtime as Runtime>::Signature: Encode + Decode,
+    <<N::Runtime as Runtime>::Signature as Verify>::Signer:
+        IdentifyAccount<AccountId = <N::Runtime as System>::AccountId>,
+{
+    let account = client
+        .keychain()
+        .get::<C::KeyType>()
+        .ok_or(DeviceCertificateError::NoAccountKey)?;
+    let network_key = client
+        .network_key()
+        .ok_or(DeviceCertificateError::NoNetworkKey)?;
+    let device = derive_device_key(&account, &network_key.public().into_peer_id());
+    let cert =
+        DeviceCertificate::<N::Runtime, C::KeyType>::new(client.signer()?, &device, network_key)?;
+    let sealed = cert.seal()?;
+    let block = Block::<OffchainConfig<N>>::encode(TreeCodec, BLAKE2B_256_TREE, &sealed.offchain)?;
+    client.offchain_client().insert(&block).await?;
+    client.devices().insert(cert)?;
+    Ok(*block.cid())
+}
+
+/// Fetches a certificate, verifies it and adds it to the device registry of
+/// the client.
+pub async fn fetch_device_certificate<N, C>(
+    client: &C,
+    cid: &Cid,
+) -> Result<DeviceCertificate<N::Runtime, C::KeyType>>
+where
+    N: Node,
+    C: Client<N>,
+    <N::Runtime as Runtime>::Signature: Encode + Decode,
+    <<N::Runtime as Runtime>::Signature as Verify>::Signer:
+        IdentifyAccount<AccountId = <N::Runtime as System>::AccountId>,
+{
+    let block = client.offchain_client().get(cid).await?;
+    let offchain: OffchainBlock<TreeHasherBlake2b256> = block.decode()?;
+    let cert: DeviceCertificate<N::Runtime, C::KeyType> = TreeDecode::decode(&offchain)?;
+    client.devices().insert(cert.clone())?;
+    Ok(cert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substrate_subxt::DefaultNodeRuntime;
+    use sunshine_crypto::keychain::TypedPair;
+    use sunshine_crypto::signer::GenericSigner;
+    use sunshine_crypto::sr25519;
+
+    struct Device;
+
+    impl KeyType for Device {
+        const KEY_TYPE: u8 = 0;
+        type Pair = sr25519::Pair;
+    }
+
+    type Certificate = DeviceCertificate<DefaultNodeRuntime, Device>;
+
+    #[async_std::test]
+    async fn test_device_certificate() {
+        let key = TypedPair::<Device>::generate().await;
+        let signer = GenericSigner::<DefaultNodeRuntime, Device>::new(key.clone());
+        let network_key = Keypair::generate_ed25519();
+        let peer_id = network_key.public().into_peer_id();
+        let device = derive_device_key(&key, &peer_id);
+        assert_ne!(device.public(), key.public());
+        let cert = Certificate::new(&signer, &device, &network_key).unwrap();
+        assert_eq!(cert.peer_id, peer_id);
+
+        let sealed = cert.seal().unwrap();
+        let cert2: Certificate = TreeDecode::decode(&sealed.offchain).unwrap();
+        let registry = DeviceRegistry::default();
+        registry.insert(cert2).unwrap();
+        assert_eq!(
+            registry.account(&peer_id).as_ref(),
+            Some(signer.account_id())
+        );
+        assert_eq!(registry.device(&peer_id), Some(device.public()));
+        assert!(registry.peers(signer.account_id()).contains(&peer_id));
+
+        let mut forged = cert.clone();
+        forged.peer_id = PeerId::random();
+        assert!(registry.insert(forged).is_err());
+
+        let mut forged = cert.clone();
+        forged.device_signature[0] ^= 1;
+        assert!(registry.insert(forged).is_err());
+
+        let mut forged = cert;
+        forged.peer_signature[0] ^= 1;
+        assert!(registry.insert(forged).is_err());
+
+        // another account can't claim the peer without its network key.
+        let key2 = TypedPair::<Device>::generate().await;
+        let signer2 = GenericSigner::<DefaultNodeRuntime, Device>::new(key2.clone());
+        let mut claim = Certificate::new(&signer2, &key2, &Keypair::generate_ed25519()).unwrap();
+        claim.peer_id = peer_id.clone();
+        let err = registry.insert(claim).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(DeviceCertificateError::InvalidPeerKey)
+        ));
+        assert_eq!(
+            registry.account(&peer_id).as_ref(),
+            Some(signer.account_id())
+        );
+
+        // the peer itself can move to another account.
+        let claim = Certificate::new(&signer2, &key2, &network_key).unwrap();
+        registry.insert(claim).unwrap();
+        assert_eq!(
+            registry.account(&peer_id).as_ref(),
+            Some(signer2.account_id())
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::Result;
+use async_std::future::timeout;
+use async_std::task;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::Stream;
+use parity_scale_codec::{Decode, Encode, Error as CodecError, Input};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use substrate_subxt::events::Raw;
+use substrate_subxt::sp_core::storage::StorageKey;
+use substrate_subxt::sp_core::twox_128;
+use substrate_subxt::sp_runtime::traits::{Header, One};
+use substrate_subxt::system::{Phase, System};
+use substrate_subxt::{Event, Runtime};
+use thiserror::Error;
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const HEADER_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_FAILURES: usize = 8;
+
+#[derive(Debug, Error)]
+#[error("block {0} not found")]
+pub struct BlockNotFound(pub String);
+
+#[derive(Debug, Error)]
+#[error("rpc call timed out")]
+pub struct RpcTimeout;
+
+#[derive(Debug, Error)]
+#[error("event subscription failed {failures} times in a row: {error}")]
+pub struct SubscriptionFailed {
+    pub failures: usize,
+    pub error: String,
+}
+
+/// Connects a new subxt client after the rpc connection failed.
+pub type Connect<T> =
+    Box<dyn Fn() -> BoxFuture<'static, Result<substrate_subxt::Client<T>>> + Send + Sync>;
+
+/// Runtime event of a finalized block.
+pub struct ChainEvent<T: Runtime> {
+    pub block_number: <T as System>::BlockNumber,
+    pub block_hash: <T as System>::Hash,
+    pub module: String,
+    pub variant: String,
+    pub data: Vec<u8>,
+}
+
+impl<T: Runtime> std::fmt::Debug for ChainEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ChainEvent")
+            .field("block_number", &self.block_number)
+            .field("block_hash", &self.block_hash)
+            .field("module", &self.module)
+            .field("variant", &self.variant)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl<T: Runtime> ChainEvent<T> {
+    /// Decodes the event if it is an `E`.
+    pub fn as_event<E: Event<T>>(&self) -> Result<Option<E>> {
+        if self.module == E::MODULE && self.variant == E::EVENT {
+            Ok(Some(E::decode(&mut &self.data[..])?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Selects the events of an event subscription.
+pub struct EventFilter<T: Runtime> {
+    /// Only events of the module.
+    pub module: Option<String>,
+    /// Only events with the name.
+    pub event: Option<String>,
+    /// Only events that contain the account id.
+    pub account: Option<<T as System>::AccountId>,
+    /// Block number to resume from, instead of the latest finalized block.
+    pub resume_from: Option<<T as System>::BlockNumber>,
+}
+
+impl<T: Runtime> Default for EventFilter<T> {
+    fn default() -> Self {
+        Self {
+            module: None,
+            event: None,
+            account: None,
+            resume_from: None,
+        }
+    }
+}
+
+impl<T: Runtime> Clone for EventFilter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            module: self.module.clone(),
+            event: self.event.clone(),
+            account: self.account.clone(),
+            resume_from: self.resume_from,
+        }
+    }
+}
+
+impl<T: Runtime> EventFilter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches events of type `E`.
+    pub fn of<E: Event<T>>() -> Self {
+        Self::new().module(E::MODULE).event(E::EVENT)
+    }
+
+    pub fn module(mut self, module: &str) -> Self {
+        self.module = Some(module.to_string());
+        self
+    }
+
+    pub fn event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    pub fn account(mut self, account: <T as System>::AccountId) -> Self {
+        self.account = Some(account);
+        self
+    }
+
+    pub fn resume_from(mut self, block_number: <T as System>::BlockNumber) -> Self {
+        self.resume_from = Some(block_number);
+        self
+    }
+
+    /// Returns true if the event passes the filter.
+    ///
+    /// The account filter matches events containing the encoded account id.
+    pub fn matches(&self, event: &ChainEvent<T>) -> bool {
+        if let Some(module) = &self.module {
+            if module != &event.module {
+                return false;
+            }
+        }
+        if let Some(name) = &self.event {
+            if name != &event.variant {
+                return false;
+            }
+        }
+        if let Some(account) = &self.account {
+            let account = account.encode();
+            if !event.data.windows(account.len()).any(|w| w == &account[..]) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Stream of the runtime events of finalized blocks.
+///
+/// Events are delivered in block order. When the rpc connection fails, or no
+/// finalized block arrives and the node doesn't respond, the client is
+/// reconnected with an exponential backoff and the subscription resumes after
+/// the last delivered block. After `MAX_FAILURES` failures in a row the
+/// stream yields a [`SubscriptionFailed`] error and ends. Blocks whose events
+/// fail to decode yield an error.
+pub struct EventStream<T: Runtime> {
+    events: UnboundedReceiver<Result<ChainEvent<T>>>,
+}
+
+impl<T: Runtime> EventStream<T> {
+    /// Creates an event stream which renews the subscription on the same
+    /// client.
+    pub fn new(client: substrate_subxt::Client<T>, filter: EventFilter<T>) -> Self {
+        let reuse = client.clone();
+        let connect: Connect<T> = Box::new(move || {
+            let client = reuse.clone();
+            async move { Ok(client) }.boxed()
+        });
+        Self::with_connect(client, filter, connect)
+    }
+
+    /// Creates an event stream which calls `connect` for a new client when
+    /// the subscription fails.
+    pub fn with_connect(
+        client: substrate_subxt::Client<T>,
+        filter: EventFilter<T>,
+        connect: Connect<T>,
+    ) -> Self {
+        let (tx, rx) = unbounded();
+        task::spawn(run(client, connect, filter, tx));
+        Self { events: rx }
+    }
+}
+
+impl<T: Runtime> Stream for EventStream<T> {
+    type Item = Result<ChainEvent<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.events).poll_next(cx)
+    }
+}
+
+type Sender<T> = UnboundedSender<Result<ChainEvent<T>>>;
+
+async fn run<T: Runtime>(
+    client: substrate_subxt::Client<T>,
+    connect: Connect<T>,
+    filter: EventFilter<T>,
+    tx: Sender<T>,
+) {
+    let mut client = Ok(client);
+    let mut next = filter.resume_from;
+    let mut backoff = MIN_BACKOFF;
+    let mut failures = 0;
+    loop {
+        let result = match &client {
+            Ok(client) => follow(client, &filter, &tx, &mut next, &mut failures).await,
+            Err(err) => Err(anyhow::Error::msg(err.to_string())),
+        };
+        let error = match result {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+        if failures == 0 {
+            backoff = MIN_BACKOFF;
+        }
+        failures += 1;
+        if failures >= MAX_FAILURES {
+            let error = SubscriptionFailed {
+                failures,
+                error: error.to_string(),
+            };
+            tx.unbounded_send(Err(error.into())).ok();
+            return;
+        }
+        task::sleep(backoff).await;
+        if tx.is_closed() {
+            return;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        client = connect().await;
+    }
+}
+
+/// Delivers the events of finalized blocks until the receiver is dropped or
+/// an rpc call fails.
+///
+/// When no finalized block arrives within `HEADER_TIMEOUT` but the node still
+/// answers, the chain is idle and the subscription is renewed.
+async fn follow<T: Runtime>(
+    client: &substrate_subxt::Client<T>,
+    filter: &EventFilter<T>,
+    tx: &Sender<T>,
+    next: &mut Option<<T as System>::BlockNumber>,
+    failures: &mut usize,
+) -> Result<()> {
+    loop {
+        let mut headers = timeout(HEADER_TIMEOUT, client.subscribe_finalized_blocks())
+            .await
+            .map_err(|_| RpcTimeout)??;
+        while let Ok(header) = timeout(HEADER_TIMEOUT, headers.next()).await {
+            if tx.is_closed() {
+                return Ok(());
+            }
+            let head = *header.number();
+            let mut number = next.unwrap_or(head);
+            while number <= head {
+                let hash = if number == head {
+                    header.hash()
+                } else {
+                    client
+                        .block_hash(Some(number.into()))
+                        .await?
+                        .ok_or_else(|| BlockNotFound(format!("{:?}", number)))?
+                };
+                let data = fetch_events(client, hash).await?;
+                let events = match decode_events(client, number, hash, &data) {
+                    Ok(events) => events,
+                    Err(err) => {
+                        if tx.unbounded_send(Err(err)).is_err() {
+                            return Ok(());
+                        }
+                        Vec::new()
+                    }
+                };
+                for (_, event) in events.into_iter().filter(|(_, ev)| filter.matches(ev)) {
+                    if tx.unbounded_send(Ok(event)).is_err() {
+                        return Ok(());
+                    }
+                }
+                number += One::one();
+                *next = Some(number);
+                *failures = 0;
+            }
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+        timeout(HEADER_TIMEOUT, client.finalized_head())
+            .await
+            .map_err(|_| RpcTimeout)??;
+    }
+}
+
+/// Storage value read without decoding.
+struct RawValue(Vec<u8>);
+
+impl Decode for RawValue {
+    fn decode<I: Input>(input: &mut I) -> std::result::Result<Self, CodecError> {
+        let len = input.remaining_len()?.unwrap_or_default();
+        let mut data = vec![0; len];
+        input.read(&mut data)?;
+        Ok(Self(data))
+    }
+}
+
+/// Fetches the encoded events of a block.
+pub(crate) async fn fetch_events<T: Runtime>(
+    client: &substrate_subxt::Client<T>,
+    hash: <T as System>::Hash,
+) -> Result<Vec<u8>> {
+    let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+    let value: Option<RawValue> = client.fetch_unhashed(key, Some(hash)).await?;
+    Ok(value.map(|value| value.0).unwrap_or_default())
+}
+
+/// Decodes the events of a block and the phase they were emitted in.
+///
+/// Runtime errors are skipped.
+pub(crate) fn decode_events<T: Runtime>(
+    client: &substrate_subxt::Client<T>,
+    block_number: <T as System>::BlockNumber,
+    block_hash: <T as System>::Hash,
+    data: &[u8],
+) -> Result<Vec<(Phase, ChainEvent<T>)>> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let events = client
+        .events_decoder()
+        .decode_events(&mut &data[..])?
+        .into_iter()
+        .filter_map(|(phase, raw)| match raw {
+            Raw::Event(event) => Some((
+                phase,
+                ChainEvent {
+                    block_number,
+                    block_hash,
+                    module: event.module,
+                    variant: event.variant,
+                    data: event.data,
+                },
+            )),
+            Raw::Error(_) => None,
+        })
+        .collect();
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substrate_subxt::balances::TransferEvent;
+    use substrate_subxt::sp_core::H256;
+    use substrate_subxt::sp_runtime::AccountId32;
+    use substrate_subxt::DefaultNodeRuntime as Runtime;
+
+    fn transfer() -> ChainEvent<Runtime> {
+        let event = TransferEvent::<Runtime> {
+            from: AccountId32::from([1; 32]),
+            to: AccountId32::from([2; 32]),
+            amount: 10_000,
+        };
+        ChainEvent {
+            block_number: 1,
+            block_hash: H256::zero(),
+            module: "Balances".into(),
+            variant: "Transfer".into(),
+            data: (&event.from, &event.to, &event.amount).encode(),
+        }
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let event = transfer();
+        assert!(EventFilter::new().matches(&event));
+        assert!(EventFilter::of::<TransferEvent<Runtime>>().matches(&event));
+        assert!(!EventFilter::new().module("System").matches(&event));
+        assert!(!EventFilter::new().event("Deposit").matches(&event));
+        let to = AccountId32::from([2; 32]);
+        assert!(EventFilter::new().account(to).matches(&event));
+        let other = AccountId32::from([3; 32]);
+        assert!(!EventFilter::new().account(other).matches(&event));
+
+        let transfer = event.as_event::<TransferEvent<Runtime>>().unwrap().unwrap();
+        assert_eq!(transfer.amount, 10_000);
+    }
+}
@@ -1,5 +1,6 @@
 pub use anyhow::{Error, Result};
 pub use async_trait::async_trait;
+pub use libipld;
 pub use sc_network;
 pub use sc_service;
 #[cfg(feature = "mock")]
@@ -10,22 +11,54 @@ pub use sunshine_crypto::keystore::{Keystore, KeystoreLocked};
 pub use sunshine_crypto::secrecy::SecretString;
 pub use sunshine_crypto::signer::Signer;
 pub use sunshine_keystore as keystore;
+pub use sunshine_node_utils::rpc::OffchainStoreHandle;
 
 mod block;
+mod chain;
 mod client;
+mod config;
+mod device;
+mod events;
+mod metrics;
+#[cfg(feature = "mock")]
+mod mock;
+mod pin;
+mod private;
+mod rpc;
+mod stats;
+#[cfg(feature = "mock")]
+mod testnet;
+mod tx;
 
 pub use block::*;
+pub use chain::*;
 pub use client::*;
+pub use config::*;
+pub use device::*;
+pub use events::*;
+pub use metrics::*;
+#[cfg(feature = "mock")]
+pub use mock::*;
+pub use pin::*;
+pub use private::*;
+pub use rpc::*;
+pub use stats::*;
+#[cfg(feature = "mock")]
+pub use testnet::*;
+pub use tx::*;
 
 use ipfs_embed::db::StorageService;
 use ipfs_embed::Ipfs;
 use libipld::store::{Store, StoreParams};
-use sc_service::{ChainSpec, Configuration, RpcHandlers, TaskManager};
+use sc_network::config::identity::Keypair;
+use sc_network::config::{NodeKeyConfig, Secret};
+use sc_service::{ChainSpec, Configuration, PruningMode, RpcHandlers, TaskManager};
 use sp_runtime::traits::Block;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use substrate_prometheus_endpoint::Registry;
 use substrate_subxt::client::{
     DatabaseConfig, KeystoreConfig, Role, SubxtClient, SubxtClientConfig,
 };
@@ -45,7 +78,31 @@ pub struct ConfigDirNotFound;
 
 #[derive(Debug, Error)]
 #[error("{0}")]
-pub struct ServiceError(String);
+pub struct ServiceError(pub String);
+
+/// Pruning mode of the state database of a full node.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pruning {
+    /// Keeps the state of all blocks.
+    Archive,
+    /// Keeps the state of the last n finalized blocks.
+    KeepBlocks(u32),
+}
+
+impl Default for Pruning {
+    fn default() -> Self {
+        Self::KeepBlocks(256)
+    }
+}
+
+impl From<Pruning> for PruningMode {
+    fn from(pruning: Pruning) -> Self {
+        match pruning {
+            Pruning::Archive => PruningMode::ArchiveAll,
+            Pruning::KeepBlocks(n) => PruningMode::keep_blocks(n),
+        }
+    }
+}
 
 pub type Network<N> = Arc<
     sc_network::NetworkService<
@@ -59,6 +116,8 @@ pub type Network<N> = Arc<
 pub struct MockNode<N: Node> {
     pub client: jsonrpsee::Client,
     pub network: Network<N>,
+    pub offchain: OffchainStoreHandle,
+    pub network_key: Keypair,
     pub tmp: tempdir::TempDir,
 }
 
@@ -74,30 +133,125 @@ pub trait Node: Clone + Copy + Unpin + Send + Sync + 'static {
     fn chain_spec_from_json_bytes(
         json: Vec<u8>,
     ) -> std::result::Result<Self::ChainSpec, ChainSpecError>;
+
+    /// Local testnet with `authorities` keyring authorities.
+    ///
+    /// Nodes can implement it with `sunshine_node_utils::chain_spec::ChainSpecBuilder`.
+    fn chain_spec_testnet(
+        authorities: usize,
+    ) -> std::result::Result<Self::ChainSpec, ChainSpecError> {
+        Err(ChainSpecError(format!(
+            "testnets with {} authorities aren't supported by this node",
+            authorities
+        )))
+    }
+
     fn new_light(
         config: Configuration,
     ) -> std::result::Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error>;
+
     fn new_full(
         config: Configuration,
     ) -> std::result::Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error>;
 
-    fn new(path: PathBuf, chain_spec: &Path) -> Result<(jsonrpsee::Client, Network<Self>)> {
-        let bytes = std::fs::read(chain_spec)?;
-        let chain_spec = Self::chain_spec_from_json_bytes(bytes)?;
-        let config = SubxtClientConfig {
-            impl_name: Self::impl_name(),
-            impl_version: Self::impl_version(),
-            author: Self::author(),
-            copyright_start_year: Self::copyright_start_year(),
-            db: DatabaseConfig::ParityDb { path },
-            keystore: KeystoreConfig::InMemory,
-            role: Role::Light,
+    /// Builds a light client whose offchain rpc methods serve the store set on
+    /// the `offchain` handle.
+    ///
+    /// Nodes built with `node_service!` implement it with the generated
+    /// `new_light_with_offchain` function. Falls back to `new_light`, which
+    /// serves an empty store.
+    fn new_light_with_offchain(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+    ) -> std::result::Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        let _ = offchain;
+        Self::new_light(config)
+    }
+
+    /// Builds a full node whose offchain rpc methods serve the store set on
+    /// the `offchain` handle.
+    ///
+    /// Nodes built with `node_service!` implement it with the generated
+    /// `new_full_with_offchain` function. Falls back to `new_full`, which
+    /// serves an empty store.
+    fn new_full_with_offchain(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+    ) -> std::result::Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        let _ = offchain;
+        Self::new_full(config)
+    }
+
+    /// Builds a full node for development.
+    ///
+    /// Nodes built with `node_service!` implement it with the generated
+    /// `new_dev_with_offchain` function, which seals a block for every
+    /// transaction with timestamps advancing by `block_time`. Fails by
+    /// default, as a full node would never seal the blocks.
+    fn new_dev(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+        block_time: Duration,
+    ) -> std::result::Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        let _ = (config, offchain, block_time);
+        Err(sc_service::Error::Other(
+            "dev nodes are not supported by this node".into(),
+        ))
+    }
+
+    /// Runs an operation on the database of a full node.
+    ///
+    /// Not supported by default. Nodes built with `node_service!` can
+    /// implement it by forwarding each operation to the generated
+    /// `export_blocks`, `import_blocks` and `check_block` functions, like the
+    /// [`TestNode`] of the `mock` feature.
+    fn chain_op(config: Configuration, op: ChainOp) -> std::result::Result<(), sc_service::Error> {
+        let _ = (config, op);
+        Err(sc_service::Error::Other(
+            "chain operations aren't supported by this node".into(),
+        ))
+    }
+
+    fn new(
+        path: PathBuf,
+        chain_spec: &Path,
+        config: &ClientConfig,
+        registry: &Registry,
+        offchain: OffchainStoreHandle,
+    ) -> Result<(jsonrpsee::Client, Network<Self>)> {
+        let chain_spec = load_chain_spec::<Self>(Some(chain_spec))?;
+        let config = service_config::<Self>(
+            path,
             chain_spec,
-            telemetry: Some(8000),
-        }
-        .into_service_config();
-        let (task_manager, rpc, network) =
-            Self::new_light(config).map_err(|e| ServiceError(format!("{}", e)))?;
+            sc_service::Role::Light,
+            config,
+            Some(registry),
+        );
+        let (task_manager, rpc, network) = Self::new_light_with_offchain(config, offchain)
+            .map_err(|e| ServiceError(format!("{}", e)))?;
+        let client = SubxtClient::new(task_manager, rpc).into();
+        Ok((client, network))
+    }
+
+    fn new_full_node(
+        path: PathBuf,
+        chain_spec: &Path,
+        config: &ClientConfig,
+        registry: &Registry,
+        pruning: Pruning,
+        offchain: OffchainStoreHandle,
+    ) -> Result<(jsonrpsee::Client, Network<Self>)> {
+        let chain_spec = load_chain_spec::<Self>(Some(chain_spec))?;
+        let mut config = service_config::<Self>(
+            path,
+            chain_spec,
+            sc_service::Role::Full,
+            config,
+            Some(registry),
+        );
+        config.pruning = pruning.into();
+        let (task_manager, rpc, network) = Self::new_full_with_offchain(config, offchain)
+            .map_err(|e| ServiceError(format!("{}", e)))?;
         let client = SubxtClient::new(task_manager, rpc).into();
         Ok((client, network))
     }
@@ -107,7 +261,7 @@ pub trait Node: Clone + Copy + Unpin + Send + Sync + 'static {
         use tempdir::TempDir;
 
         let tmp = TempDir::new("sunshine-core-").expect("failed to create tempdir");
-        let config = SubxtClientConfig {
+        let mut config = SubxtClientConfig {
             impl_name: Self::impl_name(),
             impl_version: Self::impl_version(),
             author: Self::author(),
@@ -121,16 +275,105 @@ pub trait Node: Clone + Copy + Unpin + Send + Sync + 'static {
             telemetry: None,
         }
         .into_service_config();
-        let (task_manager, rpc, network) = Self::new_full(config).unwrap();
+        let network_key = node_key_config(tmp.path())
+            .into_keypair()
+            .expect("failed to create node key");
+        config.network.node_key = node_key_config(tmp.path());
+        let offchain = OffchainStoreHandle::default();
+        let (task_manager, rpc, network) =
+            Self::new_dev(config, offchain.clone(), DEV_BLOCK_TIME).unwrap();
         let client = SubxtClient::new(task_manager, rpc).into();
         MockNode {
             client,
             network,
+            offchain,
+            network_key,
             tmp,
         }
     }
 }
 
+/// Reads a chain spec from a json file, or returns the dev chain spec.
+pub fn load_chain_spec<N: Node>(path: Option<&Path>) -> Result<N::ChainSpec> {
+    if let Some(path) = path {
+        let bytes = std::fs::read(path)?;
+        Ok(N::chain_spec_from_json_bytes(bytes)?)
+    } else {
+        Ok(N::chain_spec_dev())
+    }
+}
+
+/// Block time of the dev nodes of [`Node::new_mock`].
+///
+/// It's the slot duration of the mock runtime, the shortest block time it
+/// accepts.
+pub const DEV_BLOCK_TIME: Duration = Duration::from_millis(1000);
+
+/// Directory of the light client database in the root of a client.
+pub const LIGHT_CLIENT_DIR: &str = "light-client";
+
+/// Directory of the full node database in the root of a client.
+pub const FULL_NODE_DIR: &str = "full-node";
+
+/// File of the network key in the database directory of a node.
+pub const NODE_KEY_FILE: &str = "node-key";
+
+/// Network key of the node storing its database at `path`.
+///
+/// The key is generated when it's loaded for the first time, so the peer id
+/// of the node is kept across restarts.
+pub fn node_key_config(path: &Path) -> NodeKeyConfig {
+    NodeKeyConfig::Ed25519(Secret::File(path.join(NODE_KEY_FILE)))
+}
+
+/// Creates the configuration of a node with `role` storing its database at
+/// `path`.
+///
+/// The node exports its metrics on `registry` if prometheus is enabled, so
+/// clients pass the registry of their [`ClientMetrics`].
+pub fn service_config<N: Node>(
+    path: PathBuf,
+    chain_spec: N::ChainSpec,
+    role: sc_service::Role,
+    config: &ClientConfig,
+    registry: Option<&Registry>,
+) -> Configuration {
+    let node_key = node_key_config(&path);
+    let mut service_config = SubxtClientConfig {
+        impl_name: N::impl_name(),
+        impl_version: N::impl_version(),
+        author: N::author(),
+        copyright_start_year: N::copyright_start_year(),
+        db: DatabaseConfig::ParityDb { path },
+        keystore: KeystoreConfig::InMemory,
+        role: Role::Light,
+        chain_spec,
+        telemetry: config.telemetry_port(),
+    }
+    .into_service_config();
+    // the subxt config only knows light clients and dev authorities.
+    service_config.role = role;
+    service_config.network.node_key = node_key;
+    config.apply(&mut service_config, registry);
+    service_config
+}
+
+/// Operation on the database of a full node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ChainOp {
+    /// Exports the blocks in the range to a file.
+    ExportBlocks {
+        output: PathBuf,
+        from: u32,
+        to: Option<u32>,
+        binary: bool,
+    },
+    /// Imports blocks from a file.
+    ImportBlocks { input: PathBuf, binary: bool },
+    /// Reimports a block given by number or hex encoded hash.
+    CheckBlock { block: String },
+}
+
 /// The client trait.
 #[async_trait]
 pub trait Client<N: Node>: Send + Sync {
@@ -144,7 +387,25 @@ pub trait Client<N: Node>: Send + Sync {
     type OffchainClient: OffchainClient<OffchainStore<N>>;
 
     /// Returns the network service.
-    fn network(&self) -> &Network<N>;
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client is connected to a remote node, which doesn't run
+    /// a network service. Use `try_network` for clients that might be remote.
+    fn network(&self) -> &Network<N> {
+        self.try_network()
+            .expect("client is connected to a remote node")
+    }
+
+    /// Returns the network service, or `None` if the client is connected to
+    /// a remote node.
+    fn try_network(&self) -> Option<&Network<N>>;
+
+    /// Returns the network key of the node, which proves the peer id of the
+    /// client.
+    ///
+    /// Clients connected to a remote node don't have a network key.
+    fn network_key(&self) -> Option<&Keypair>;
 
     /// Returns a reference to the keystore.
     fn keystore(&self) -> &Self::Keystore;
@@ -195,14 +456,36 @@ pub trait Client<N: Node>: Send + Sync {
     /// Returns a reference to the subxt client.
     fn chain_client(&self) -> &substrate_subxt::Client<N::Runtime>;
 
+    /// Returns the rpc client the subxt client is connected with.
+    fn rpc_client(&self) -> &jsonrpsee::Client;
+
+    /// Returns the journal of pending transactions.
+    fn tx_journal(&self) -> &TxJournal;
+
     /// Returns a reference to the offchain client.
     fn offchain_client(&self) -> &Self::OffchainClient;
+
+    /// Returns a reference to the pin sets of the offchain store.
+    fn pins(&self) -> &Pins<OffchainStore<N>>;
+
+    /// Returns the counters of the offchain store.
+    fn offchain_stats(&self) -> Result<OffchainStats>;
+
+    /// Returns the offchain network for managing its peers.
+    fn offchain_network(&self) -> &OffchainNetwork<N>;
+
+    /// Subscribes to the runtime events of finalized blocks.
+    fn subscribe_events(&self, filter: EventFilter<N::Runtime>) -> EventStream<N::Runtime> {
+        EventStream::new(self.chain_client().clone(), filter)
+    }
+
+    /// Returns the verified device certificates.
+    fn devices(&self) -> &DeviceRegistry<N::Runtime, Self::KeyType>;
 }
 
 pub type OffchainNetwork<N> =
     SubstrateNetwork<<N as Node>::Block, <<N as Node>::Block as Block>::Hash, OffchainConfig<N>>;
-pub type OffchainStore<N> =
-    Ipfs<OffchainConfig<N>, StorageService<OffchainConfig<N>>, OffchainNetwork<N>>;
+pub type OffchainStore<N> = Ipfs<OffchainConfig<N>, OffchainStorage<N>, OffchainNetwork<N>>;
 
 /// The offchain client trait.
 pub trait OffchainClient<S: Store>: Deref<Target = S> + From<S> + Send + Sync {}
@@ -213,7 +496,11 @@ pub struct OffchainConfig<N: Node> {
     pub cache_size: usize,
     pub sweep_interval: Duration,
     pub network_timeout: Duration,
-    pub network: Network<N>,
+    /// Network to exchange blocks with, or `None` to only use local blocks.
+    pub network: Option<Network<N>>,
+    pub path: Option<PathBuf>,
+    /// Metrics of the client the store belongs to.
+    pub metrics: ClientMetrics,
 }
 
 impl<N: Node> StoreParams for OffchainConfig<N> {
@@ -224,12 +511,23 @@ impl<N: Node> StoreParams for OffchainConfig<N> {
 
 impl<N: Node> OffchainConfig<N> {
     pub fn new(network: Network<N>) -> Self {
+        Self::with_network(Some(network))
+    }
+
+    /// Creates a config for a store that doesn't exchange blocks with peers.
+    pub fn offline() -> Self {
+        Self::with_network(None)
+    }
+
+    fn with_network(network: Option<Network<N>>) -> Self {
         Self {
             db_config: sled::Config::new(),
             cache_size: 1000,
             sweep_interval: Duration::from_secs(30),
             network_timeout: Duration::from_secs(3),
             network,
+            path: None,
+            metrics: ClientMetrics::new(),
         }
     }
 
@@ -239,21 +537,32 @@ impl<N: Node> OffchainConfig<N> {
     }
 
     pub fn path<T: AsRef<Path>>(mut self, path: T) -> Self {
-        self.db_config = self.db_config.path(path);
+        self.db_config = self.db_config.path(&path);
+        self.path = Some(path.as_ref().to_path_buf());
         self
     }
 
-    pub fn build(self) -> Result<OffchainStore<N>> {
-        let offchain_storage = Arc::new(StorageService::open(
-            &self.db_config,
-            self.cache_size,
-            self.sweep_interval,
-        )?);
-        let offchain_network = Arc::new(SubstrateNetwork::<_, _, Self>::new(self.network));
-        Ok(Ipfs::new(
-            offchain_storage,
-            offchain_network,
-            self.network_timeout,
-        ))
+    pub fn metrics(mut self, metrics: ClientMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn build(self) -> Result<(OffchainStore<N>, OffchainMonitor<N>)> {
+        let offchain_storage = Arc::new(OffchainStorage::new(
+            StorageService::open(&self.db_config, self.cache_size, self.sweep_interval)?,
+            self.metrics.clone(),
+        ));
+        let offchain_network = Arc::new(match self.network {
+            Some(network) => SubstrateNetwork::<_, _, Self>::new(network),
+            None => SubstrateNetwork::offline(),
+        });
+        offchain_network.set_metrics(self.metrics.network.clone());
+        let monitor = OffchainMonitor::new(
+            offchain_storage.clone(),
+            offchain_network.clone(),
+            self.path,
+        );
+        let store = Ipfs::new(offchain_storage, offchain_network, self.network_timeout);
+        Ok((store, monitor))
     }
 }
@@ -0,0 +1,121 @@
+use crate::Result;
+use async_std::task::{self, JoinHandle};
+use std::net::SocketAddr;
+use substrate_prometheus_endpoint::{
+    init_prometheus, register, Counter, PrometheusError, Registry, U64,
+};
+use sunshine_client_net::NetworkMetrics;
+use sunshine_crypto::metrics::register_metrics;
+
+/// Prometheus metrics of a client.
+///
+/// Every client registers its metrics on its own registry, which is passed to
+/// the node service of the client when the prometheus exporter is enabled in
+/// the [`ClientConfig`](crate::ClientConfig).
+#[derive(Clone)]
+pub struct ClientMetrics {
+    registry: Registry,
+    pub(crate) keystore_unlock_failures: Counter<U64>,
+    pub(crate) offchain_block_inserts: Counter<U64>,
+    pub(crate) offchain_block_gets: Counter<U64>,
+    pub(crate) offchain_local_hits: Counter<U64>,
+    pub(crate) offchain_local_misses: Counter<U64>,
+    pub(crate) network: NetworkMetrics,
+}
+
+impl ClientMetrics {
+    /// Creates the metrics on a new registry.
+    pub fn new() -> Self {
+        // same prefix as the default registry of the node service, so the
+        // substrate metrics keep their names.
+        let registry =
+            Registry::new_custom(Some("substrate".into()), None).expect("prefix is valid; qed");
+        Self::register(registry).expect("metrics are registered once on a new registry; qed")
+    }
+
+    fn register(registry: Registry) -> Result<Self, PrometheusError> {
+        let counter = |name: &str, help: &str| register(Counter::new(name, help)?, &registry);
+        register_metrics(&registry)?;
+        Ok(Self {
+            keystore_unlock_failures: counter(
+                "sunshine_keystore_unlock_failures_total",
+                "Number of failed attempts to unlock the keystore",
+            )?,
+            offchain_block_inserts: counter(
+                "sunshine_offchain_block_inserts_total",
+                "Number of blocks inserted into the offchain store",
+            )?,
+            offchain_block_gets: counter(
+                "sunshine_offchain_block_gets_total",
+                "Number of blocks requested from the offchain store",
+            )?,
+            offchain_local_hits: counter(
+                "sunshine_offchain_local_hits_total",
+                "Number of lookups that found the block in the local offchain store",
+            )?,
+            offchain_local_misses: counter(
+                "sunshine_offchain_local_misses_total",
+                "Number of lookups that didn't find the block in the local offchain store",
+            )?,
+            network: NetworkMetrics::register(&registry)?,
+            registry,
+        })
+    }
+
+    /// Registry the metrics are registered on.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Serves the registry over http.
+    ///
+    /// Clients running a node serve the metrics through the node service, so
+    /// this is only needed for clients connected to a remote node.
+    pub fn serve(&self, addr: SocketAddr) -> JoinHandle<Result<()>> {
+        let registry = self.registry.clone();
+        task::spawn(async move {
+            init_prometheus(addr, registry).await?;
+            Ok(())
+        })
+    }
+}
+
+impl Default for ClientMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(metrics: &ClientMetrics) -> Vec<String> {
+        metrics
+            .registry()
+            .gather()
+            .into_iter()
+            .map(|family| family.get_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_metrics_registry() {
+        let metrics = ClientMetrics::new();
+        metrics.keystore_unlock_failures.inc();
+        let names = names(&metrics);
+        assert!(names.contains(&"substrate_sunshine_keystore_unlock_failures_total".to_string()));
+        assert!(names.contains(&"substrate_sunshine_signatures_total".to_string()));
+    }
+
+    #[test]
+    fn test_metrics_per_client() {
+        let light = ClientMetrics::new();
+        let full = ClientMetrics::new();
+        light.keystore_unlock_failures.inc();
+        assert_eq!(light.keystore_unlock_failures.get(), 1);
+        assert_eq!(full.keystore_unlock_failures.get(), 0);
+        // the crypto metrics are process wide and shared by both registries.
+        assert!(names(&full).contains(&"substrate_sunshine_signatures_total".to_string()));
+    }
+}
@@ -0,0 +1,247 @@
+//! Node of the mock runtime.
+use crate::{
+    ChainOp, ChainSpecError, GenericClient, Network, Node, OffchainClient, OffchainStore,
+    OffchainStoreHandle,
+};
+use libipld::store::Store;
+use sc_service::{Configuration, RpcHandlers, TaskManager};
+use std::ops::Deref;
+use std::time::Duration;
+use substrate_subxt::DefaultNodeRuntime;
+use sunshine_crypto::keystore::mock::DeviceKey;
+use sunshine_node_utils::mock;
+
+/// Node running the mock runtime of `sunshine_node_utils`.
+#[derive(Clone, Copy)]
+pub struct TestNode;
+
+impl Node for TestNode {
+    type ChainSpec = mock::ChainSpec;
+    type Runtime = DefaultNodeRuntime;
+    type Block = mock::runtime::OpaqueBlock;
+
+    fn impl_name() -> &'static str {
+        "sunshine-test-node"
+    }
+
+    fn impl_version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn author() -> &'static str {
+        env!("CARGO_PKG_AUTHORS")
+    }
+
+    fn copyright_start_year() -> i32 {
+        2020
+    }
+
+    fn chain_spec_dev() -> Self::ChainSpec {
+        mock::dev_chain_spec()
+    }
+
+    fn chain_spec_from_json_bytes(json: Vec<u8>) -> Result<Self::ChainSpec, ChainSpecError> {
+        mock::ChainSpec::from_json_bytes(json).map_err(ChainSpecError)
+    }
+
+    fn chain_spec_testnet(authorities: usize) -> Result<Self::ChainSpec, ChainSpecError> {
+        mock::testnet_chain_spec(authorities).map_err(|err| ChainSpecError(err.to_string()))
+    }
+
+    fn new_light(
+        config: Configuration,
+    ) -> Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        mock::new_light(config)
+    }
+
+    fn new_full(
+        config: Configuration,
+    ) -> Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        mock::new_full(config)
+    }
+
+    fn new_light_with_offchain(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+    ) -> Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        mock::new_light_with_offchain(config, offchain)
+    }
+
+    fn new_full_with_offchain(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+    ) -> Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        mock::new_full_with_offchain(config, offchain)
+    }
+
+    fn new_dev(
+        config: Configuration,
+        offchain: OffchainStoreHandle,
+        block_time: Duration,
+    ) -> Result<(TaskManager, RpcHandlers, Network<Self>), sc_service::Error> {
+        mock::new_dev_with_offchain(config, offchain, block_time)
+    }
+
+    fn chain_op(config: Configuration, op: ChainOp) -> Result<(), sc_service::Error> {
+        match op {
+            ChainOp::ExportBlocks {
+                output,
+                from,
+                to,
+                binary,
+            } => mock::export_blocks(config, &output, from, to, binary),
+            ChainOp::ImportBlocks { input, binary } => mock::import_blocks(config, &input, binary),
+            ChainOp::CheckBlock { block } => mock::check_block(config, &block),
+        }
+    }
+}
+
+/// Offchain client without any extensions.
+pub struct TestOffchainClient<S>(S);
+
+impl<S: Store> From<S> for TestOffchainClient<S> {
+    fn from(store: S) -> Self {
+        Self(store)
+    }
+}
+
+impl<S: Store> Deref for TestOffchainClient<S> {
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<S: Store> OffchainClient<S> for TestOffchainClient<S> {}
+
+/// Client of the [`TestNode`].
+pub type TestClient =
+    GenericClient<TestNode, DeviceKey, TestOffchainClient<OffchainStore<TestNode>>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        publish_device_certificate, service_config, Client, ClientConfig, OffchainConfig, Pruning,
+        TxConfig, TxSubmitter, FULL_NODE_DIR,
+    };
+    use jsonrpsee::common::{to_value as to_json_value, Params};
+    use libipld::block::Block;
+    use libipld::cbor::DagCborCodec;
+    use libipld::ipld::Ipld;
+    use sp_keyring::AccountKeyring;
+    use substrate_subxt::balances::{TransferCall, TransferEvent};
+    use substrate_subxt::sp_core::Bytes;
+    use sunshine_codec::hasher::BLAKE2B_256;
+    use sunshine_node_utils::chain_spec::DEFAULT_ENDOWMENT;
+    use tempdir::TempDir;
+
+    #[async_std::test]
+    async fn test_account_rpc() {
+        let node = TestNode::new_mock();
+        let (client, _tmp) = TestClient::mock(&node, AccountKeyring::Alice).await;
+        let rpc = client.rpc_client();
+        let alice = to_json_value(AccountKeyring::Alice.to_account_id()).unwrap();
+
+        let params = Params::Array(vec![alice.clone()]);
+        let nonce: u32 = rpc.request("account_nonce", params).await.unwrap();
+        assert_eq!(nonce, 0);
+
+        let params = Params::Array(vec![alice]);
+        let balance: u128 = rpc.request("account_freeBalance", params).await.unwrap();
+        assert_eq!(balance, DEFAULT_ENDOWMENT);
+    }
+
+    #[async_std::test]
+    async fn test_publish_device_certificate() {
+        let node = TestNode::new_mock();
+        let (client, _tmp) = TestClient::mock(&node, AccountKeyring::Alice).await;
+        publish_device_certificate(&client).await.unwrap();
+        let peer_id = client.offchain_network().local_peer_id();
+        assert_eq!(
+            client.devices().account(peer_id),
+            Some(AccountKeyring::Alice.to_account_id())
+        );
+    }
+
+    #[async_std::test]
+    async fn test_dev_transfer() {
+        let node = TestNode::new_mock();
+        let (client, _tmp) = TestClient::mock(&node, AccountKeyring::Alice).await;
+        let chain = client.chain_client();
+
+        // dev nodes don't author blocks in aura slots, only for transactions.
+        async_std::task::sleep(Duration::from_secs(3)).await;
+        let head = chain.finalized_head().await.unwrap();
+        let header = chain.header(Some(head)).await.unwrap().unwrap();
+        assert_eq!(header.number, 0);
+
+        let bob = AccountKeyring::Bob.to_account_id().into();
+        let call = TransferCall {
+            to: &bob,
+            amount: 10_000,
+        };
+        let outcome = TxSubmitter::new(&client, TxConfig::default())
+            .submit(call, &mut |_| {})
+            .await
+            .unwrap();
+        assert!(outcome.find_event::<TransferEvent<_>>().unwrap().is_some());
+        let header = chain
+            .header(Some(outcome.block_hash))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.number, 1);
+    }
+
+    #[async_std::test]
+    async fn test_offchain_rpc() {
+        let node = TestNode::new_mock();
+        let (client, _tmp) = TestClient::mock(&node, AccountKeyring::Alice).await;
+        let rpc = client.rpc_client();
+        let block = Block::<OffchainConfig<TestNode>>::encode(
+            DagCborCodec,
+            BLAKE2B_256,
+            &Ipld::String("block".into()),
+        )
+        .unwrap();
+        let cid = to_json_value(Bytes(block.cid().to_bytes())).unwrap();
+        let data = to_json_value(Bytes(block.data().to_vec())).unwrap();
+
+        let params = Params::Array(vec![cid.clone(), to_json_value(Bytes(vec![0])).unwrap()]);
+        let res: Result<(), _> = rpc.request("offchain_putBlock", params).await;
+        assert!(res.is_err());
+
+        let params = Params::Array(vec![cid.clone(), data]);
+        let _: () = rpc.request("offchain_putBlock", params).await.unwrap();
+        let stored = client.offchain_client().get(block.cid()).await.unwrap();
+        assert_eq!(stored.data(), block.data());
+
+        let params = Params::Array(vec![cid]);
+        let data: Option<Bytes> = rpc.request("offchain_getBlock", params).await.unwrap();
+        assert_eq!(data.unwrap().0, block.data().to_vec());
+    }
+
+    #[test]
+    fn test_chain_op() {
+        let tmp = TempDir::new("sunshine-chain-op-").unwrap();
+        let mut config = service_config::<TestNode>(
+            tmp.path().join(FULL_NODE_DIR),
+            TestNode::chain_spec_dev(),
+            sc_service::Role::Full,
+            &ClientConfig::default(),
+            None,
+        );
+        config.pruning = Pruning::Archive.into();
+        let output = tmp.path().join("blocks.json");
+        let op = ChainOp::ExportBlocks {
+            output: output.clone(),
+            from: 0,
+            to: None,
+            binary: false,
+        };
+        TestNode::chain_op(config, op).unwrap();
+        assert!(std::fs::metadata(&output).unwrap().len() > 0);
+    }
+}
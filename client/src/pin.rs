@@ -0,0 +1,319 @@
+use crate::Result;
+use async_std::sync::Mutex;
+use libipld::block::Block;
+use libipld::cbor::DagCborCodec;
+use libipld::codec::Decode;
+use libipld::ipld::Ipld;
+use libipld::store::{Store, StoreParams};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use sunshine_codec::codec::Multicodec;
+use sunshine_codec::hasher::BLAKE2B_256;
+use sunshine_codec::Cid;
+use thiserror::Error;
+
+/// Alias of the block containing all pin sets.
+const PIN_SETS: &[u8] = b"sunshine-pin-sets";
+
+#[derive(Debug, Error)]
+#[error("Corrupted pin set")]
+pub struct CorruptedPinSet;
+
+/// Storage usage of pinned blocks.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PinReport {
+    /// Number of blocks reachable from a pin.
+    pub pinned_blocks: u64,
+    /// Size of the blocks reachable from a pin.
+    pub pinned_size: u64,
+    /// Size of the store on disk if it isn't temporary.
+    pub total_size: Option<u64>,
+}
+
+impl PinReport {
+    /// Size of the blocks that will eventually be swept.
+    pub fn unpinned_size(&self) -> Option<u64> {
+        self.total_size
+            .map(|total| total.saturating_sub(self.pinned_size))
+    }
+}
+
+/// Named sets of pinned blocks.
+///
+/// The pin sets are stored as dag-cbor blocks linking to the pinned roots,
+/// which are kept alive by a single alias. Sweeps never drop a block that is
+/// reachable from an alias, so pinning a root pins everything it references.
+pub struct Pins<S: Store> {
+    store: S,
+    path: Option<PathBuf>,
+    lock: Mutex<()>,
+}
+
+impl<S> Pins<S>
+where
+    S: Store,
+    S::Params: StoreParams<Codecs = Multicodec>,
+{
+    /// Creates a pin manager for the store located at `path`.
+    pub fn new(store: S, path: Option<PathBuf>) -> Self {
+        Self {
+            store,
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Returns all pin sets.
+    pub async fn sets(&self) -> Result<BTreeMap<String, Vec<Cid>>> {
+        let mut sets = BTreeMap::new();
+        for (name, cid) in self.read_sets().await? {
+            sets.insert(name, self.read_set(&cid).await?);
+        }
+        Ok(sets)
+    }
+
+    /// Returns the roots pinned by a pin set.
+    pub async fn set(&self, name: &str) -> Result<Vec<Cid>> {
+        if let Some(cid) = self.read_sets().await?.get(name) {
+            self.read_set(cid).await
+        } else {
+            Ok(Default::default())
+        }
+    }
+
+    /// Returns the names of the pin sets pinning `cid`.
+    pub async fn pinned_by(&self, cid: &Cid) -> Result<Vec<String>> {
+        Ok(self
+            .sets()
+            .await?
+            .into_iter()
+            .filter(|(_, roots)| roots.contains(cid))
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Pins `cid` and everything it references in the pin set `name`.
+    ///
+    /// All referenced blocks are fetched, so that they are available locally.
+    pub async fn pin(&self, name: &str, cid: &Cid) -> Result<()> {
+        self.walk(&[*cid]).await?;
+        let _guard = self.lock.lock().await;
+        let mut sets = self.read_sets().await?;
+        let mut roots = if let Some(set) = sets.get(name) {
+            self.read_set(set).await?
+        } else {
+            Default::default()
+        };
+        if roots.contains(cid) {
+            return Ok(());
+        }
+        roots.push(*cid);
+        sets.insert(name.to_string(), self.write_set(roots).await?);
+        self.write_sets(sets).await
+    }
+
+    /// Removes `cid` from the pin set `name`.
+    ///
+    /// The blocks are dropped by the next sweep unless they are pinned by
+    /// another pin.
+    pub async fn unpin(&self, name: &str, cid: &Cid) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut sets = self.read_sets().await?;
+        let mut roots = if let Some(set) = sets.get(name) {
+            self.read_set(set).await?
+        } else {
+            return Ok(());
+        };
+        roots.retain(|root| root != cid);
+        if roots.is_empty() {
+            sets.remove(name);
+        } else {
+            sets.insert(name.to_string(), self.write_set(roots).await?);
+        }
+        self.write_sets(sets).await
+    }
+
+    /// Removes the pin set `name`.
+    pub async fn remove_set(&self, name: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut sets = self.read_sets().await?;
+        if sets.remove(name).is_some() {
+            self.write_sets(sets).await?;
+        }
+        Ok(())
+    }
+
+    /// Reports the storage used by pinned blocks.
+    pub async fn report(&self) -> Result<PinReport> {
+        let mut roots = Vec::new();
+        for (_, set) in self.sets().await? {
+            roots.extend(set);
+        }
+        let (pinned_blocks, pinned_size) = self.walk(&roots).await?;
+        let total_size = if let Some(path) = self.path.as_ref() {
+            Some(dir_size(path)?)
+        } else {
+            None
+        };
+        Ok(PinReport {
+            pinned_blocks,
+            pinned_size,
+            total_size,
+        })
+    }
+
+    /// Fetches all blocks reachable from `roots` and returns their count and size.
+    async fn walk(&self, roots: &[Cid]) -> Result<(u64, u64)> {
+        let mut visited = HashSet::new();
+        let mut stack = roots.to_vec();
+        let mut size = 0;
+        while let Some(cid) = stack.pop() {
+            if !visited.insert(cid) {
+                continue;
+            }
+            let block = self.store.get(&cid).await?;
+            size += block.data().len() as u64;
+            if let Ok(codec) = Multicodec::try_from(cid.codec()) {
+                let ipld = Ipld::decode(codec, &mut block.data())?;
+                stack.extend(ipld.references());
+            }
+        }
+        Ok((visited.len() as u64, size))
+    }
+
+    async fn read_sets(&self) -> Result<BTreeMap<String, Cid>> {
+        let cid = if let Some(cid) = self.store.resolve(PIN_SETS).await? {
+            cid
+        } else {
+            return Ok(Default::default());
+        };
+        match self.read(&cid).await? {
+            Ipld::Map(map) => map
+                .into_iter()
+                .map(|(name, link)| match link {
+                    Ipld::Link(cid) => Ok((name, cid)),
+                    _ => Err(CorruptedPinSet.into()),
+                })
+                .collect(),
+            _ => Err(CorruptedPinSet.into()),
+        }
+    }
+
+    async fn write_sets(&self, sets: BTreeMap<String, Cid>) -> Result<()> {
+        let map = sets
+            .into_iter()
+            .map(|(name, cid)| (name, Ipld::Link(cid)))
+            .collect();
+        let cid = self.write(&Ipld::Map(map)).await?;
+        self.store.alias(PIN_SETS, Some(&cid)).await?;
+        Ok(())
+    }
+
+    async fn read_set(&self, cid: &Cid) -> Result<Vec<Cid>> {
+        match self.read(cid).await? {
+            Ipld::List(list) => list
+                .into_iter()
+                .map(|link| match link {
+                    Ipld::Link(cid) => Ok(cid),
+                    _ => Err(CorruptedPinSet.into()),
+                })
+                .collect(),
+            _ => Err(CorruptedPinSet.into()),
+        }
+    }
+
+    async fn write_set(&self, roots: Vec<Cid>) -> Result<Cid> {
+        let list = roots.into_iter().map(Ipld::Link).collect();
+        self.write(&Ipld::List(list)).await
+    }
+
+    async fn read(&self, cid: &Cid) -> Result<Ipld> {
+        let block = self.store.get(cid).await?;
+        Ok(Ipld::decode(DagCborCodec, &mut block.data())?)
+    }
+
+    async fn write(&self, ipld: &Ipld) -> Result<Cid> {
+        let block = Block::<S::Params>::encode(DagCborCodec, BLAKE2B_256, ipld)?;
+        self.store.insert(&block).await?;
+        Ok(*block.cid())
+    }
+}
+
+pub(crate) fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    if !path.exists() {
+        return Ok(size);
+    }
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OffchainChain;
+    use libipld::mem::MemStore;
+    use sunshine_codec::hasher::Multihash;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl StoreParams for Params {
+        type Hashes = Multihash;
+        type Codecs = Multicodec;
+        const MAX_BLOCK_SIZE: usize = u16::MAX as _;
+    }
+
+    #[async_std::test]
+    async fn test_pins() {
+        let store = MemStore::<Params>::default();
+        let mut chain = OffchainChain::<_, String>::new(store.clone());
+        chain.append("genesis".into()).await.unwrap();
+        let genesis = chain.head().unwrap();
+        chain.append("one".into()).await.unwrap();
+        let one = chain.head().unwrap();
+
+        let pins = Pins::new(store, None);
+        pins.pin("heads", &one).await.unwrap();
+        pins.pin("heads", &one).await.unwrap();
+        pins.pin("genesis", &genesis).await.unwrap();
+        assert_eq!(pins.set("heads").await.unwrap(), vec![one]);
+        assert_eq!(
+            pins.pinned_by(&genesis).await.unwrap(),
+            vec!["genesis".to_string()]
+        );
+
+        let report = pins.report().await.unwrap();
+        assert_eq!(report.pinned_blocks, 2);
+        assert_eq!(report.total_size, None);
+
+        pins.unpin("heads", &one).await.unwrap();
+        assert!(pins.set("heads").await.unwrap().is_empty());
+        pins.remove_set("genesis").await.unwrap();
+        assert!(pins.sets().await.unwrap().is_empty());
+        assert_eq!(pins.report().await.unwrap().pinned_blocks, 0);
+    }
+
+    #[async_std::test]
+    async fn test_pin_chain_head() {
+        let store = MemStore::<Params>::default();
+        let mut chain = OffchainChain::<_, String>::new(store.clone());
+        chain.append("genesis".into()).await.unwrap();
+        chain.append("one".into()).await.unwrap();
+        let head = chain.head().unwrap();
+
+        let pins = Pins::new(store, None);
+        pins.pin("heads", &head).await.unwrap();
+        let report = pins.report().await.unwrap();
+        assert_eq!(report.pinned_blocks, 2);
+    }
+}
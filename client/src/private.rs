@@ -0,0 +1,133 @@
+use crate::{Client, Node, OffchainConfig, Result};
+use libipld::block::Block;
+use libipld::store::{Store, StoreParams};
+use parity_scale_codec::{Decode, Encode};
+use std::collections::BTreeMap;
+use sunshine_codec::codec::{Multicodec, Secret, SecretCodec, TreeCodec, SECRET};
+use sunshine_codec::hasher::{Multihash, TreeHasherBlake2b256, BLAKE2B_256};
+use sunshine_codec::trie::OffchainBlock;
+use sunshine_codec::Cid;
+use sunshine_crypto::keychain::{KeyChain, KeyType};
+use sunshine_crypto::secret_box::SecretBox;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Block isn't encrypted")]
+pub struct NotEncrypted;
+
+type Tree = BTreeMap<String, Vec<u8>>;
+
+/// Encrypts an offchain block to the group `K` of the keychain.
+pub async fn encrypt_block<K, P>(
+    keychain: &KeyChain,
+    block: &OffchainBlock<TreeHasherBlake2b256>,
+) -> Result<Block<P>>
+where
+    K: KeyType,
+    P: StoreParams<Codecs = Multicodec, Hashes = Multihash>,
+{
+    let secret = SecretBox::<K, Tree>::encrypt(keychain, block.tree()).await?;
+    Ok(Block::encode(
+        SecretCodec,
+        BLAKE2B_256,
+        &Secret(secret.encode()),
+    )?)
+}
+
+/// Decrypts an offchain block encrypted to the group `K`.
+pub fn decrypt_block<K, P>(
+    keychain: &KeyChain,
+    block: &Block<P>,
+) -> Result<OffchainBlock<TreeHasherBlake2b256>>
+where
+    K: KeyType,
+    P: StoreParams<Codecs = Multicodec, Hashes = Multihash>,
+{
+    if block.cid().codec() != SECRET {
+        return Err(NotEncrypted.into());
+    }
+    let secret: SecretBox<K, Tree> = Decode::decode(&mut block.data())?;
+    let tree = secret.decrypt(keychain)?;
+    Ok(libipld::codec::Decode::decode(
+        TreeCodec,
+        &mut &tree.encode()[..],
+    )?)
+}
+
+/// Encrypts an offchain block to the group `K` and inserts it into the
+/// offchain store.
+///
+/// The block is only sent to peers authorized for the group.
+pub async fn insert_private<N, C, K>(
+    client: &C,
+    block: &OffchainBlock<TreeHasherBlake2b256>,
+) -> Result<Cid>
+where
+    N: Node,
+    C: Client<N>,
+    K: KeyType,
+{
+    let block = encrypt_block::<K, OffchainConfig<N>>(client.keychain(), block).await?;
+    // protect the block before it can be served.
+    client.offchain_network().protect(*block.cid(), K::KEY_TYPE);
+    client.offchain_client().insert(&block).await?;
+    Ok(*block.cid())
+}
+
+/// Fetches and decrypts an offchain block encrypted to the group `K`.
+pub async fn get_private<N, C, K>(
+    client: &C,
+    cid: &Cid,
+) -> Result<OffchainBlock<TreeHasherBlake2b256>>
+where
+    N: Node,
+    C: Client<N>,
+    K: KeyType,
+{
+    let block = client.offchain_client().get(cid).await?;
+    decrypt_block::<K, _>(client.keychain(), &block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sunshine_codec::trie::BlockBuilder;
+    use sunshine_crypto::keychain::TypedPair;
+    use sunshine_crypto::sr25519;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl StoreParams for Params {
+        type Hashes = Multihash;
+        type Codecs = Multicodec;
+        const MAX_BLOCK_SIZE: usize = u16::MAX as _;
+    }
+
+    struct Team;
+
+    impl KeyType for Team {
+        const KEY_TYPE: u8 = 2;
+        type Pair = sr25519::Pair;
+    }
+
+    #[async_std::test]
+    async fn test_private_block() {
+        let mut alice = KeyChain::new();
+        let mut bob = KeyChain::new();
+        let eve = KeyChain::new();
+        let key = TypedPair::<Team>::generate().await;
+        bob.insert(key.clone());
+        alice.insert(key);
+
+        let mut builder = BlockBuilder::<TreeHasherBlake2b256>::new();
+        builder.insert("payload".into(), &"secret".to_string(), false);
+        let block = builder.seal().unwrap().offchain;
+
+        let secret = encrypt_block::<Team, Params>(&alice, &block).await.unwrap();
+        assert_eq!(secret.cid().codec(), SECRET);
+        let block2 = decrypt_block::<Team, _>(&bob, &secret).unwrap();
+        assert_eq!(block, block2);
+        assert!(decrypt_block::<Team, _>(&eve, &secret).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{Node, OffchainConfig, OffchainStore};
+use async_std::task;
+use libipld::block::Block;
+use libipld::error::BlockNotFound;
+use libipld::store::Store;
+use std::convert::TryFrom;
+use sunshine_codec::Cid;
+use sunshine_node_utils::rpc::OffchainBlockStore;
+
+/// Offchain store served by the offchain rpc methods of the node.
+///
+/// Set it on the `OffchainStoreHandle` the node was built with.
+pub struct OffchainRpcStore<N: Node>(OffchainStore<N>);
+
+impl<N: Node> OffchainRpcStore<N> {
+    pub fn new(store: OffchainStore<N>) -> Self {
+        Self(store)
+    }
+}
+
+impl<N: Node> OffchainBlockStore for OffchainRpcStore<N> {
+    fn get(&self, cid: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let cid = Cid::try_from(cid).map_err(|err| err.to_string())?;
+        match task::block_on(self.0.get(&cid)) {
+            Ok(block) => Ok(Some(block.data().to_vec())),
+            Err(err) if err.downcast_ref::<BlockNotFound>().is_some() => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn insert(&self, cid: &[u8], data: Vec<u8>) -> Result<(), String> {
+        let cid = Cid::try_from(cid).map_err(|err| err.to_string())?;
+        let block = Block::<OffchainConfig<N>>::new(cid, data.into_boxed_slice())
+            .map_err(|err| err.to_string())?;
+        task::block_on(self.0.insert(&block)).map_err(|err| err.to_string())
+    }
+}
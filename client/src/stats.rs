@@ -0,0 +1,166 @@
+use crate::pin::dir_size;
+use crate::{ClientMetrics, Node, OffchainConfig, OffchainNetwork, Result};
+use ipfs_embed::db::StorageService;
+use ipfs_embed_core::Storage;
+use libipld::block::Block;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use sunshine_client_net::NetworkStats;
+use sunshine_codec::Cid;
+
+/// Counters of the offchain storage.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StorageStats {
+    /// Number of lookups that found the block in the local store.
+    ///
+    /// Blocks fetched from the network are inserted into the local store, so
+    /// later lookups of them are counted here as well.
+    pub local_hits: u64,
+    /// Number of lookups that didn't find the block in the local store.
+    pub local_misses: u64,
+    /// Number of blocks inserted since the store was opened.
+    pub inserted_blocks: u64,
+    /// Number of bytes inserted since the store was opened.
+    pub inserted_bytes: u64,
+    /// Size of the store on disk if it isn't temporary.
+    pub disk_size: Option<u64>,
+}
+
+impl StorageStats {
+    /// Ratio of lookups that found the block in the local store.
+    pub fn local_hit_rate(&self) -> Option<f64> {
+        let total = self.local_hits + self.local_misses;
+        if total == 0 {
+            None
+        } else {
+            Some(self.local_hits as f64 / total as f64)
+        }
+    }
+}
+
+/// Counters of the offchain store.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OffchainStats {
+    pub storage: StorageStats,
+    pub network: NetworkStats,
+}
+
+#[derive(Default)]
+struct Counters {
+    local_hits: AtomicU64,
+    local_misses: AtomicU64,
+    inserted_blocks: AtomicU64,
+    inserted_bytes: AtomicU64,
+}
+
+/// Storage service that counts the requests to the underlying storage.
+pub struct OffchainStorage<N: Node> {
+    storage: StorageService<OffchainConfig<N>>,
+    counters: Counters,
+    metrics: ClientMetrics,
+}
+
+impl<N: Node> OffchainStorage<N> {
+    pub fn new(storage: StorageService<OffchainConfig<N>>, metrics: ClientMetrics) -> Self {
+        Self {
+            storage,
+            counters: Default::default(),
+            metrics,
+        }
+    }
+}
+
+impl<N: Node> Storage<OffchainConfig<N>> for OffchainStorage<N> {
+    type Subscription =
+        <StorageService<OffchainConfig<N>> as Storage<OffchainConfig<N>>>::Subscription;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>> {
+        let data = self.storage.get(cid)?;
+        let metrics = &self.metrics;
+        metrics.offchain_block_gets.inc();
+        let (counter, metric) = if data.is_some() {
+            (&self.counters.local_hits, &metrics.offchain_local_hits)
+        } else {
+            (&self.counters.local_misses, &metrics.offchain_local_misses)
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        metric.inc();
+        Ok(data)
+    }
+
+    fn insert(&self, block: &Block<OffchainConfig<N>>) -> Result<()> {
+        self.storage.insert(block)?;
+        self.metrics.offchain_block_inserts.inc();
+        self.counters
+            .inserted_blocks
+            .fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .inserted_bytes
+            .fetch_add(block.data().len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn alias(&self, alias: &[u8], cid: Option<&Cid>) -> Result<()> {
+        self.storage.alias(alias, cid)
+    }
+
+    fn resolve(&self, alias: &[u8]) -> Result<Option<Cid>> {
+        self.storage.resolve(alias)
+    }
+
+    fn subscribe(&self) -> Self::Subscription {
+        self.storage.subscribe()
+    }
+}
+
+/// Handle for reading the counters of an offchain store.
+pub struct OffchainMonitor<N: Node> {
+    storage: Arc<OffchainStorage<N>>,
+    network: Arc<OffchainNetwork<N>>,
+    path: Option<PathBuf>,
+}
+
+impl<N: Node> OffchainMonitor<N> {
+    pub(crate) fn new(
+        storage: Arc<OffchainStorage<N>>,
+        network: Arc<OffchainNetwork<N>>,
+        path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            storage,
+            network,
+            path,
+        }
+    }
+
+    /// Returns the offchain network.
+    pub fn network(&self) -> &OffchainNetwork<N> {
+        &self.network
+    }
+
+    /// Returns the metrics the store records.
+    pub fn metrics(&self) -> &ClientMetrics {
+        &self.storage.metrics
+    }
+
+    /// Returns the current counters.
+    pub fn stats(&self) -> Result<OffchainStats> {
+        let counters = &self.storage.counters;
+        let disk_size = if let Some(path) = self.path.as_ref() {
+            Some(dir_size(path)?)
+        } else {
+            None
+        };
+        Ok(OffchainStats {
+            storage: StorageStats {
+                local_hits: counters.local_hits.load(Ordering::Relaxed),
+                local_misses: counters.local_misses.load(Ordering::Relaxed),
+                inserted_blocks: counters.inserted_blocks.load(Ordering::Relaxed),
+                inserted_bytes: counters.inserted_bytes.load(Ordering::Relaxed),
+                disk_size,
+            },
+            network: self.network.stats(),
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{node_key_config, Network, Node, OffchainStoreHandle, Result, ServiceError};
+use async_std::task;
+use sc_network::config::identity::Keypair;
+use sc_network::config::MultiaddrWithPeerId;
+use sc_network::{Multiaddr, PeerId};
+use sp_keyring::AccountKeyring;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use substrate_subxt::client::{
+    DatabaseConfig, KeystoreConfig, Role, SubxtClient, SubxtClientConfig,
+};
+use substrate_subxt::sp_runtime::traits::Header;
+use substrate_subxt::ClientBuilder;
+use tempdir::TempDir;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("testnet didn't finalize block {0} in time")]
+pub struct FinalityTimeout(pub u32);
+
+#[derive(Debug, Error)]
+#[error("testnet node {0} isn't running")]
+pub struct NodeStopped(pub usize);
+
+/// Authority node of a [`Testnet`].
+pub struct TestnetNode<N: Node> {
+    /// Keyring account of the authority.
+    pub keyring: AccountKeyring,
+    /// Directory of the database and node key, kept across restarts.
+    pub tmp: TempDir,
+    /// Address the node listens on.
+    pub addr: Multiaddr,
+    /// Peer id of the node, kept across restarts.
+    pub peer_id: PeerId,
+    /// Offchain store served by the rpc of the node, kept across restarts.
+    pub offchain: OffchainStoreHandle,
+    /// Network key of the node, kept across restarts.
+    pub network_key: Keypair,
+    service: Option<(jsonrpsee::Client, Network<N>)>,
+}
+
+impl<N: Node> TestnetNode<N> {
+    pub fn is_running(&self) -> bool {
+        self.service.is_some()
+    }
+
+    pub fn client(&self) -> Option<&jsonrpsee::Client> {
+        self.service.as_ref().map(|(client, _)| client)
+    }
+
+    pub fn network(&self) -> Option<&Network<N>> {
+        self.service.as_ref().map(|(_, network)| network)
+    }
+
+    pub fn boot_node(&self) -> MultiaddrWithPeerId {
+        MultiaddrWithPeerId {
+            multiaddr: self.addr.clone(),
+            peer_id: self.peer_id.clone(),
+        }
+    }
+}
+
+/// In-process network of authority nodes.
+///
+/// The nodes run the local testnet chain spec of the node and connect to
+/// each other on localhost. A node shuts down when it is stopped and all
+/// clients connected to it are dropped.
+pub struct Testnet<N: Node> {
+    chain_spec: N::ChainSpec,
+    nodes: Vec<TestnetNode<N>>,
+}
+
+impl<N: Node> Testnet<N> {
+    /// Starts `n` authority nodes using `Node::chain_spec_testnet`.
+    pub fn start(n: usize) -> Result<Self> {
+        let chain_spec = N::chain_spec_testnet(n)?;
+        let mut nodes: Vec<TestnetNode<N>> = Vec::with_capacity(n);
+        for keyring in AccountKeyring::iter().take(n) {
+            let tmp = TempDir::new("sunshine-testnet-")?;
+            let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{}", free_port()?).parse()?;
+            let boot_nodes = nodes.iter().map(|node| node.boot_node()).collect();
+            let offchain = OffchainStoreHandle::default();
+            let (client, network) = start_node::<N>(
+                &chain_spec,
+                keyring,
+                tmp.path(),
+                &addr,
+                boot_nodes,
+                offchain.clone(),
+            )?;
+            let network_key = node_key_config(tmp.path()).into_keypair()?;
+            nodes.push(TestnetNode {
+                keyring,
+                tmp,
+                addr,
+                peer_id: network.local_peer_id().clone(),
+                offchain,
+                network_key,
+                service: Some((client, network)),
+            });
+        }
+        Ok(Self { chain_spec, nodes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, i: usize) -> &TestnetNode<N> {
+        &self.nodes[i]
+    }
+
+    pub fn nodes(&self) -> &[TestnetNode<N>] {
+        &self.nodes
+    }
+
+    /// Stops a node.
+    pub fn stop(&mut self, i: usize) {
+        self.nodes[i].service = None;
+    }
+
+    /// Restarts a stopped node from its database.
+    ///
+    /// Waits for the previous instance to release the database.
+    pub async fn restart(&mut self, i: usize) -> Result<()> {
+        self.stop(i);
+        let boot_nodes: Vec<_> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, node)| node.boot_node())
+            .collect();
+        let node = &self.nodes[i];
+        let mut attempts = 0;
+        let service = loop {
+            match start_node::<N>(
+                &self.chain_spec,
+                node.keyring,
+                node.tmp.path(),
+                &node.addr,
+                boot_nodes.clone(),
+                node.offchain.clone(),
+            ) {
+                Ok(service) => break service,
+                Err(_) if attempts < 20 => {
+                    attempts += 1;
+                    task::sleep(Duration::from_millis(250)).await;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        self.nodes[i].service = Some(service);
+        Ok(())
+    }
+
+    /// Waits until all running nodes finalized the block.
+    pub async fn wait_for_finalized(&self, number: u32, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        for node in self.nodes.iter() {
+            let client = if let Some(client) = node.client() {
+                ClientBuilder::<N::Runtime>::new()
+                    .set_client(client.clone())
+                    .build()
+                    .await?
+            } else {
+                continue;
+            };
+            loop {
+                let hash = client.finalized_head().await?;
+                if let Some(header) = client.header(Some(hash)).await? {
+                    if *header.number() >= number.into() {
+                        break;
+                    }
+                }
+                if Instant::now() > deadline {
+                    return Err(FinalityTimeout(number).into());
+                }
+                task::sleep(Duration::from_millis(100)).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Client of a running node.
+    pub fn client(&self, i: usize) -> Result<&jsonrpsee::Client> {
+        Ok(self.nodes[i].client().ok_or(NodeStopped(i))?)
+    }
+
+    /// Network of a running node.
+    pub fn network(&self, i: usize) -> Result<&Network<N>> {
+        Ok(self.nodes[i].network().ok_or(NodeStopped(i))?)
+    }
+}
+
+fn free_port() -> Result<u16> {
+    Ok(std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port())
+}
+
+fn start_node<N: Node>(
+    chain_spec: &N::ChainSpec,
+    keyring: AccountKeyring,
+    path: &Path,
+    addr: &Multiaddr,
+    boot_nodes: Vec<MultiaddrWithPeerId>,
+    offchain: OffchainStoreHandle,
+) -> Result<(jsonrpsee::Client, Network<N>)> {
+    let mut config = SubxtClientConfig {
+        impl_name: N::impl_name(),
+        impl_version: N::impl_version(),
+        author: N::author(),
+        copyright_start_year: N::copyright_start_year(),
+        db: DatabaseConfig::ParityDb {
+            path: path.join("db"),
+        },
+        keystore: KeystoreConfig::InMemory,
+        chain_spec: chain_spec.clone(),
+        role: Role::Authority(keyring),
+        telemetry: None,
+    }
+    .into_service_config();
+    config.network.listen_addresses = vec![addr.clone()];
+    config.network.boot_nodes.extend(boot_nodes);
+    config.network.node_key = node_key_config(path);
+    let (task_manager, rpc, network) =
+        N::new_full_with_offchain(config, offchain).map_err(|e| ServiceError(e.to_string()))?;
+    let client = SubxtClient::new(task_manager, rpc).into();
+    Ok((client, network))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestNode;
+
+    #[async_std::test]
+    async fn test_testnet_restart() {
+        let timeout = Duration::from_secs(60);
+        let mut testnet = Testnet::<TestNode>::start(2).unwrap();
+        testnet.wait_for_finalized(2, timeout).await.unwrap();
+
+        // grandpa needs both authorities, so finality only resumes after the
+        // node is back.
+        testnet.stop(1);
+        assert!(testnet.client(1).is_err());
+        task::sleep(Duration::from_secs(2)).await;
+        testnet.restart(1).await.unwrap();
+        testnet.wait_for_finalized(6, timeout).await.unwrap();
+    }
+}
@@ -0,0 +1,507 @@
+use crate::events::{decode_events, fetch_events};
+use crate::{BlockNotFound, ChainEvent, Client, Node, Result};
+use async_std::future::timeout;
+use async_std::task;
+use jsonrpsee::client::Subscription;
+use jsonrpsee::common::{to_value as to_json_value, Params};
+use parity_scale_codec::{Decode, Encode};
+use sp_transaction_pool::TransactionStatus;
+use sp_version::RuntimeVersion;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use substrate_subxt::balances::Balances;
+use substrate_subxt::extrinsic::{
+    ChargeTransactionPayment, CheckEra, CheckGenesis, CheckNonce, CheckSpecVersion, CheckTxVersion,
+    CheckWeight, SignedPayload,
+};
+use substrate_subxt::sp_core::Bytes;
+use substrate_subxt::sp_runtime::generic::Era;
+use substrate_subxt::sp_runtime::traits::{Header, SaturatedConversion, SignedExtension};
+use substrate_subxt::system::{AccountStoreExt, Phase, System};
+use substrate_subxt::{Call, Encoded, Event, Runtime, SignedExtra, UncheckedExtrinsic};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("transaction dropped after {0} attempts")]
+pub struct TxDropped(pub u32);
+
+#[derive(Debug, Error)]
+#[error("transaction status unknown after {0} submissions, it might still be finalized")]
+pub struct TxUnknown(pub u32);
+
+#[derive(Debug, Error)]
+#[error("extrinsic not found in block {0}")]
+pub struct ExtrinsicNotFound(pub String);
+
+#[derive(Debug, Error)]
+#[error("transaction invalid after {attempts} attempts: {reason}")]
+pub struct TxInvalid {
+    pub attempts: u32,
+    pub reason: String,
+}
+
+/// Stage of a submitted transaction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TxStage<H> {
+    /// The transaction is ready to be included in a block.
+    Ready,
+    /// The transaction was broadcast to the peers.
+    Broadcast(Vec<String>),
+    /// The transaction was included in a block.
+    InBlock(H),
+    /// The block including the transaction was finalized.
+    Finalized(H),
+    /// The transaction was removed from the pool without being finalized.
+    Dropped,
+    /// It isn't known if the transaction will be finalized, because it was
+    /// dropped after it was included in a block, finality timed out or the
+    /// node stopped reporting its status.
+    Unknown,
+    /// The transaction was rejected by the pool.
+    Invalid,
+}
+
+impl<H: std::fmt::Debug> std::fmt::Display for TxStage<H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Ready => write!(f, "ready"),
+            Self::Broadcast(peers) => write!(f, "broadcast to {} peers", peers.len()),
+            Self::InBlock(hash) => write!(f, "in block {:?}", hash),
+            Self::Finalized(hash) => write!(f, "finalized in block {:?}", hash),
+            Self::Dropped => write!(f, "dropped"),
+            Self::Unknown => write!(f, "unknown"),
+            Self::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+/// Period a transaction is valid for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mortality {
+    /// The transaction is valid forever.
+    Immortal,
+    /// The transaction is valid for a number of blocks after the latest
+    /// finalized block.
+    Mortal(u64),
+}
+
+impl Default for Mortality {
+    fn default() -> Self {
+        Self::Mortal(64)
+    }
+}
+
+/// Configuration of the transaction submitter.
+#[derive(Clone, Debug)]
+pub struct TxConfig {
+    /// Number of times a dropped or invalid transaction is signed with a
+    /// recomputed nonce and submitted again. A transaction whose status is
+    /// unknown is submitted again unchanged as often.
+    pub retries: u32,
+    /// Time to wait before retrying.
+    pub backoff: Duration,
+    /// Time to wait for the next status of a submitted transaction.
+    pub timeout: Duration,
+    /// Period the transaction is valid for.
+    pub mortality: Mortality,
+}
+
+impl Default for TxConfig {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(300),
+            mortality: Mortality::default(),
+        }
+    }
+}
+
+/// Signed extensions that carry the era of a transaction.
+///
+/// It is implemented for the signed extensions of the default subxt runtimes,
+/// where the `CheckEra` is the fourth extension. Runtimes with other signed
+/// extensions need to implement it for their extensions.
+pub trait MortalExtra<T: Runtime>: SignedExtension {
+    /// Sets the era and the hash of the block the era starts at.
+    fn set_era(&mut self, era: Era, checkpoint: <T as System>::Hash);
+}
+
+impl<T: Runtime + Balances> MortalExtra<T>
+    for (
+        CheckSpecVersion<T>,
+        CheckTxVersion<T>,
+        CheckGenesis<T>,
+        CheckEra<T>,
+        CheckNonce<T>,
+        CheckWeight<T>,
+        ChargeTransactionPayment<T>,
+    )
+{
+    fn set_era(&mut self, era: Era, checkpoint: <T as System>::Hash) {
+        self.3 = CheckEra((era, PhantomData), checkpoint);
+    }
+}
+
+/// Transaction that wasn't finalized yet.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+pub struct PendingTx {
+    pub id: u64,
+    /// Encoded call.
+    pub call: Vec<u8>,
+    /// Last submitted extrinsic.
+    pub extrinsic: Option<Vec<u8>>,
+    /// Number of times the transaction was submitted.
+    pub attempts: u32,
+}
+
+/// Journal of pending transactions.
+///
+/// Transactions are recorded before they are submitted and removed when
+/// they are finalized or failed, so that they can be resumed after a
+/// restart.
+pub struct TxJournal {
+    path: Option<PathBuf>,
+    txs: Mutex<Vec<PendingTx>>,
+}
+
+impl TxJournal {
+    /// Opens the journal at `path` or an in-memory journal.
+    pub fn open(path: Option<PathBuf>) -> Result<Self> {
+        let txs = match &path {
+            Some(path) if path.exists() => Decode::decode(&mut &std::fs::read(path)?[..])?,
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            path,
+            txs: Mutex::new(txs),
+        })
+    }
+
+    /// Returns the pending transactions.
+    pub fn pending(&self) -> Vec<PendingTx> {
+        self.txs.lock().unwrap().clone()
+    }
+
+    pub(crate) fn insert(&self, call: Vec<u8>) -> Result<u64> {
+        let mut txs = self.txs.lock().unwrap();
+        let id = txs.iter().map(|tx| tx.id + 1).max().unwrap_or_default();
+        txs.push(PendingTx {
+            id,
+            call,
+            extrinsic: None,
+            attempts: 0,
+        });
+        self.write(&txs)?;
+        Ok(id)
+    }
+
+    pub(crate) fn update(&self, id: u64, extrinsic: Vec<u8>, attempts: u32) -> Result<()> {
+        let mut txs = self.txs.lock().unwrap();
+        if let Some(tx) = txs.iter_mut().find(|tx| tx.id == id) {
+            tx.extrinsic = Some(extrinsic);
+            tx.attempts = attempts;
+        }
+        self.write(&txs)
+    }
+
+    pub(crate) fn remove(&self, id: u64) -> Result<()> {
+        let mut txs = self.txs.lock().unwrap();
+        txs.retain(|tx| tx.id != id);
+        self.write(&txs)
+    }
+
+    fn write(&self, txs: &[PendingTx]) -> Result<()> {
+        if let Some(path) = &self.path {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, txs.encode())?;
+            std::fs::rename(&tmp, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finalized transaction.
+pub struct TxOutcome<T: Runtime> {
+    pub block_hash: <T as System>::Hash,
+    pub extrinsic_index: u32,
+    /// Events emitted by the transaction.
+    pub events: Vec<ChainEvent<T>>,
+}
+
+impl<T: Runtime> TxOutcome<T> {
+    /// Returns the first event of type `E`.
+    pub fn find_event<E: Event<T>>(&self) -> Result<Option<E>> {
+        for event in &self.events {
+            if let Some(event) = event.as_event()? {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+}
+
+enum Watched<H> {
+    Finalized(H),
+    /// The transaction won't be included, so it can be signed again.
+    Dropped,
+    /// The transaction might still be included, so it must not be signed
+    /// again.
+    Unknown,
+    Invalid(String),
+}
+
+/// Signs, submits and watches transactions.
+///
+/// Each attempt is signed with the current nonce of the account. A
+/// transaction that is dropped or rejected is retried up to `retries` times.
+/// A transaction that might still be included is submitted again unchanged,
+/// so that it can't be executed twice.
+pub struct TxSubmitter<'a, N: Node, C: Client<N>> {
+    _marker: PhantomData<N>,
+    client: &'a C,
+    config: TxConfig,
+}
+
+impl<'a, N, C> TxSubmitter<'a, N, C>
+where
+    N: Node,
+    C: Client<N>,
+    <<N::Runtime as Runtime>::Extra as SignedExtra<N::Runtime>>::Extra: MortalExtra<N::Runtime>,
+{
+    pub fn new(client: &'a C, config: TxConfig) -> Self {
+        Self {
+            _marker: PhantomData,
+            client,
+            config,
+        }
+    }
+
+    /// Submits a call and waits for it to be finalized.
+    pub async fn submit<T: Call<N::Runtime> + Send>(
+        &self,
+        call: T,
+        report: &mut (dyn FnMut(&TxStage<<N::Runtime as System>::Hash>) + Send),
+    ) -> Result<TxOutcome<N::Runtime>> {
+        let call = self.client.chain_client().encode(call)?;
+        let id = self.client.tx_journal().insert(call.0.clone())?;
+        self.run(id, call, None, 0, report).await
+    }
+
+    /// Resumes the transactions of the journal.
+    ///
+    /// The last submitted extrinsic is submitted again first, so that a
+    /// transaction that was finalized before the restart is rejected by the
+    /// pool instead of being executed twice.
+    pub async fn resume(
+        &self,
+        report: &mut (dyn FnMut(&TxStage<<N::Runtime as System>::Hash>) + Send),
+    ) -> Result<Vec<Result<TxOutcome<N::Runtime>>>> {
+        let mut outcomes = Vec::new();
+        for tx in self.client.tx_journal().pending() {
+            let call = Encoded(tx.call);
+            let outcome = self
+                .run(tx.id, call, tx.extrinsic, tx.attempts, report)
+                .await;
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
+    }
+
+    async fn run(
+        &self,
+        id: u64,
+        call: Encoded,
+        mut extrinsic: Option<Vec<u8>>,
+        mut attempts: u32,
+        report: &mut (dyn FnMut(&TxStage<<N::Runtime as System>::Hash>) + Send),
+    ) -> Result<TxOutcome<N::Runtime>> {
+        let mut resubmits = 0;
+        let result = loop {
+            let (ext, stored) = match extrinsic.take() {
+                Some(ext) => (ext, true),
+                None => {
+                    attempts += 1;
+                    (self.sign(&call).await?.encode(), false)
+                }
+            };
+            self.client.tx_journal().update(id, ext.clone(), attempts)?;
+            match self.watch(&ext, report).await? {
+                Watched::Finalized(block_hash) => break self.outcome(block_hash, &ext).await,
+                // the stored extrinsic might have been finalized before the
+                // restart, so it isn't signed again.
+                Watched::Invalid(reason) if stored => {
+                    break Err(TxInvalid { attempts, reason }.into());
+                }
+                Watched::Unknown if resubmits < self.config.retries => {
+                    resubmits += 1;
+                    extrinsic = Some(ext);
+                }
+                Watched::Unknown => break Err(TxUnknown(resubmits + 1).into()),
+                Watched::Dropped | Watched::Invalid(_) if attempts <= self.config.retries => {}
+                Watched::Dropped => break Err(TxDropped(attempts).into()),
+                Watched::Invalid(reason) => break Err(TxInvalid { attempts, reason }.into()),
+            }
+            task::sleep(self.config.backoff).await;
+        };
+        self.client.tx_journal().remove(id)?;
+        result
+    }
+
+    async fn sign(&self, call: &Encoded) -> Result<UncheckedExtrinsic<N::Runtime>> {
+        let chain = self.client.chain_client();
+        let signer = self.client.signer()?;
+        let nonce = chain.account(signer.account_id(), None).await?.nonce;
+        let version: RuntimeVersion = self
+            .client
+            .rpc_client()
+            .request("state_getRuntimeVersion", Params::None)
+            .await?;
+        let mut extra = <N::Runtime as Runtime>::Extra::new(
+            version.spec_version,
+            version.transaction_version,
+            nonce,
+            *chain.genesis(),
+        )
+        .extra();
+        if let Mortality::Mortal(period) = self.config.mortality {
+            let checkpoint = chain.finalized_head().await?;
+            let header = chain
+                .header(Some(checkpoint))
+                .await?
+                .ok_or_else(|| BlockNotFound(format!("{:?}", checkpoint)))?;
+            let number = (*header.number()).saturated_into::<u64>();
+            extra.set_era(Era::mortal(period, number), checkpoint);
+        }
+        let payload = SignedPayload::new(call.clone(), extra).map_err(|err| TxInvalid {
+            attempts: 0,
+            reason: format!("{:?}", err),
+        })?;
+        Ok(signer.sign_extrinsic(payload))
+    }
+
+    async fn watch(
+        &self,
+        ext: &[u8],
+        report: &mut (dyn FnMut(&TxStage<<N::Runtime as System>::Hash>) + Send),
+    ) -> Result<Watched<<N::Runtime as System>::Hash>> {
+        let params = Params::Array(vec![to_json_value(Bytes(ext.to_vec()))?]);
+        let subscription = self
+            .client
+            .rpc_client()
+            .subscribe(
+                "author_submitAndWatchExtrinsic",
+                params,
+                "author_unwatchExtrinsic",
+            )
+            .await;
+        let mut subscription: Subscription<TransactionStatus<_, _>> = match subscription {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                report(&TxStage::Invalid);
+                return Ok(Watched::Invalid(err.to_string()));
+            }
+        };
+        let mut in_block = false;
+        loop {
+            let status = match timeout(self.config.timeout, subscription.next()).await {
+                Ok(status) => status,
+                Err(_) => {
+                    report(&TxStage::Unknown);
+                    return Ok(Watched::Unknown);
+                }
+            };
+            match status {
+                TransactionStatus::Future | TransactionStatus::Retracted(_) => {}
+                TransactionStatus::Ready => report(&TxStage::Ready),
+                TransactionStatus::Broadcast(peers) => report(&TxStage::Broadcast(peers)),
+                TransactionStatus::InBlock(hash) => {
+                    in_block = true;
+                    report(&TxStage::InBlock(hash));
+                }
+                TransactionStatus::Finalized(hash) => {
+                    report(&TxStage::Finalized(hash));
+                    return Ok(Watched::Finalized(hash));
+                }
+                TransactionStatus::FinalityTimeout(_) => {
+                    report(&TxStage::Unknown);
+                    return Ok(Watched::Unknown);
+                }
+                TransactionStatus::Dropped if in_block => {
+                    report(&TxStage::Unknown);
+                    return Ok(Watched::Unknown);
+                }
+                TransactionStatus::Usurped(_) | TransactionStatus::Dropped => {
+                    report(&TxStage::Dropped);
+                    return Ok(Watched::Dropped);
+                }
+                TransactionStatus::Invalid => {
+                    report(&TxStage::Invalid);
+                    return Ok(Watched::Invalid("rejected by the pool".into()));
+                }
+            }
+        }
+    }
+
+    async fn outcome(
+        &self,
+        block_hash: <N::Runtime as System>::Hash,
+        ext: &[u8],
+    ) -> Result<TxOutcome<N::Runtime>> {
+        let chain = self.client.chain_client();
+        let block = chain
+            .block(Some(block_hash))
+            .await?
+            .ok_or_else(|| BlockNotFound(format!("{:?}", block_hash)))?;
+        let extrinsic_index = block
+            .block
+            .extrinsics
+            .iter()
+            .position(|opaque| opaque.encode() == ext)
+            .ok_or_else(|| ExtrinsicNotFound(format!("{:?}", block_hash)))?
+            as u32;
+        let number = *block.block.header.number();
+        let data = fetch_events(chain, block_hash).await?;
+        let events = decode_events(chain, number, block_hash, &data)?
+            .into_iter()
+            .filter(|(phase, _)| *phase == Phase::ApplyExtrinsic(extrinsic_index))
+            .map(|(_, event)| event)
+            .collect();
+        Ok(TxOutcome {
+            block_hash,
+            extrinsic_index,
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_tx_journal() {
+        let tmp = TempDir::new("sunshine-tx-journal-").unwrap();
+        let path = tmp.path().join("tx-journal");
+        let journal = TxJournal::open(Some(path.clone())).unwrap();
+        let id1 = journal.insert(vec![1]).unwrap();
+        let id2 = journal.insert(vec![2]).unwrap();
+        assert_ne!(id1, id2);
+        journal.update(id2, vec![2, 2], 1).unwrap();
+        journal.remove(id1).unwrap();
+
+        let journal = TxJournal::open(Some(path)).unwrap();
+        assert_eq!(
+            journal.pending(),
+            vec![PendingTx {
+                id: id2,
+                call: vec![2],
+                extrinsic: Some(vec![2, 2]),
+                attempts: 1,
+            }]
+        );
+    }
+}
@@ -26,6 +26,53 @@ impl TryFrom<u64> for TreeCodec {
     }
 }
 
+/// Codec of encrypted blocks.
+///
+/// The data is opaque, so encrypted blocks don't have references.
+#[derive(Clone, Copy, Debug)]
+pub struct SecretCodec;
+
+impl Codec for SecretCodec {}
+
+impl From<SecretCodec> for u64 {
+    fn from(_: SecretCodec) -> Self {
+        SECRET
+    }
+}
+
+impl TryFrom<u64> for SecretCodec {
+    type Error = UnsupportedCodec;
+
+    fn try_from(_: u64) -> core::result::Result<Self, Self::Error> {
+        Ok(Self)
+    }
+}
+
+/// Encrypted block data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Secret(pub Vec<u8>);
+
+impl Encode<SecretCodec> for Secret {
+    fn encode<W: Write>(&self, _: SecretCodec, w: &mut W) -> Result<()> {
+        w.write_all(&self.0)?;
+        Ok(())
+    }
+}
+
+impl Decode<SecretCodec> for Secret {
+    fn decode<R: Read>(_: SecretCodec, r: &mut R) -> Result<Self> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        Ok(Self(data))
+    }
+}
+
+impl Decode<SecretCodec> for Ipld {
+    fn decode<R: Read>(c: SecretCodec, r: &mut R) -> Result<Self> {
+        Ok(Ipld::Bytes(Secret::decode(c, r)?.0))
+    }
+}
+
 pub(crate) struct IoReader<R: Read>(pub R);
 
 impl<R: Read> parity_scale_codec::Input for IoReader<R> {
@@ -60,11 +107,13 @@ impl Decode<TreeCodec> for Ipld {
 
 pub const DAG_CBOR: u64 = libipld::cid::DAG_CBOR; //0x00;
 pub const SCALE_TREE: u64 = 0x01;
+pub const SECRET: u64 = 0x02;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Multicodec {
     DagCbor,
     Tree,
+    Secret,
 }
 
 impl TryFrom<u64> for Multicodec {
@@ -74,6 +123,7 @@ impl TryFrom<u64> for Multicodec {
         Ok(match ccode {
             DAG_CBOR => Self::DagCbor,
             SCALE_TREE => Self::Tree,
+            SECRET => Self::Secret,
             _ => return Err(UnsupportedCodec(ccode)),
         })
     }
@@ -84,6 +134,7 @@ impl From<Multicodec> for u64 {
         match mc {
             Multicodec::DagCbor => DAG_CBOR,
             Multicodec::Tree => SCALE_TREE,
+            Multicodec::Secret => SECRET,
         }
     }
 }
@@ -112,6 +163,18 @@ impl From<Multicodec> for TreeCodec {
     }
 }
 
+impl From<SecretCodec> for Multicodec {
+    fn from(_: SecretCodec) -> Self {
+        Self::Secret
+    }
+}
+
+impl From<Multicodec> for SecretCodec {
+    fn from(_: Multicodec) -> Self {
+        Self
+    }
+}
+
 impl Codec for Multicodec {}
 
 impl Encode<Multicodec> for Ipld {
@@ -119,6 +182,7 @@ impl Encode<Multicodec> for Ipld {
         match c {
             Multicodec::DagCbor => self.encode(DagCborCodec, w)?,
             Multicodec::Tree => return Err(UnsupportedCodec(Multicodec::Tree.into()).into()),
+            Multicodec::Secret => return Err(UnsupportedCodec(Multicodec::Secret.into()).into()),
         };
         Ok(())
     }
@@ -129,6 +193,7 @@ impl Decode<Multicodec> for Ipld {
         Ok(match c {
             Multicodec::DagCbor => Self::decode(DagCborCodec, r)?,
             Multicodec::Tree => Self::decode(TreeCodec, r)?,
+            Multicodec::Secret => Self::decode(SecretCodec, r)?,
         })
     }
 }
@@ -4,6 +4,8 @@
 pub mod codec;
 pub mod hasher;
 #[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "std")]
 pub mod trie;
 
 #[cfg(feature = "std")]
@@ -0,0 +1,382 @@
+use crate::trie::{Hasher, OffchainBlock, TreeMap, TreeOption, TreeVec};
+use parity_scale_codec::{Compact, Decode, Input};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tiny_cid::Cid;
+
+/// Scale type descriptor of a value stored in the tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeDescriptor {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    String,
+    Bytes,
+    FixedBytes(usize),
+    Cid,
+    Option(Box<TypeDescriptor>),
+    Vec(Box<TypeDescriptor>),
+    Tuple(Vec<TypeDescriptor>),
+    /// Value without a known structure, rendered as hex.
+    Opaque,
+}
+
+impl TypeDescriptor {
+    /// Decodes a value and renders it as json.
+    pub fn decode(&self, input: &mut &[u8]) -> Result<Value, parity_scale_codec::Error> {
+        Ok(match self {
+            Self::Bool => Value::Bool(Decode::decode(input)?),
+            Self::U8 => u8::decode(input)?.into(),
+            Self::U16 => u16::decode(input)?.into(),
+            Self::U32 => u32::decode(input)?.into(),
+            Self::U64 => u64::decode(input)?.into(),
+            Self::U128 => u128::decode(input)?.to_string().into(),
+            Self::I8 => i8::decode(input)?.into(),
+            Self::I16 => i16::decode(input)?.into(),
+            Self::I32 => i32::decode(input)?.into(),
+            Self::I64 => i64::decode(input)?.into(),
+            Self::I128 => i128::decode(input)?.to_string().into(),
+            Self::String => String::decode(input)?.into(),
+            Self::Bytes => hex(&Vec::<u8>::decode(input)?).into(),
+            Self::FixedBytes(len) => {
+                let mut buf = vec![0; *len];
+                input.read(&mut buf)?;
+                hex(&buf).into()
+            }
+            Self::Cid => <Cid as Decode>::decode(input)?.to_string().into(),
+            Self::Option(inner) => match u8::decode(input)? {
+                0 => Value::Null,
+                1 => inner.decode(input)?,
+                _ => return Err("invalid option discriminant".into()),
+            },
+            Self::Vec(inner) => {
+                let len = <Compact<u32>>::decode(input)?.0;
+                // the length isn't trusted, so the preallocation is capped
+                // at the remaining input.
+                let mut items = Vec::with_capacity((len as usize).min(input.len()));
+                for _ in 0..len {
+                    items.push(inner.decode(input)?);
+                }
+                Value::Array(items)
+            }
+            Self::Tuple(inner) => {
+                let mut items = Vec::with_capacity(inner.len());
+                for ty in inner {
+                    items.push(ty.decode(input)?);
+                }
+                Value::Array(items)
+            }
+            Self::Opaque => {
+                let value = hex(*input).into();
+                *input = &[];
+                value
+            }
+        })
+    }
+}
+
+/// Types with a scale type descriptor.
+pub trait Describe {
+    fn describe() -> TypeDescriptor;
+}
+
+macro_rules! describe {
+    ($($ty:ty => $desc:ident,)*) => {
+        $(
+            impl Describe for $ty {
+                fn describe() -> TypeDescriptor {
+                    TypeDescriptor::$desc
+                }
+            }
+        )*
+    };
+}
+
+describe! {
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    u128 => U128,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    i128 => I128,
+    String => String,
+    Cid => Cid,
+}
+
+impl<T: Describe> Describe for Option<T> {
+    fn describe() -> TypeDescriptor {
+        TypeDescriptor::Option(Box::new(T::describe()))
+    }
+}
+
+impl<T: Describe> Describe for Vec<T> {
+    fn describe() -> TypeDescriptor {
+        TypeDescriptor::Vec(Box::new(T::describe()))
+    }
+}
+
+impl<A: Describe, B: Describe> Describe for (A, B) {
+    fn describe() -> TypeDescriptor {
+        TypeDescriptor::Tuple(vec![A::describe(), B::describe()])
+    }
+}
+
+/// Field names and type descriptors of a tree encoded type.
+///
+/// A `*` path segment matches any single segment, which is how the elements
+/// of collections are described.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Schema {
+    fields: BTreeMap<String, TypeDescriptor>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(&mut self, key: String, ty: TypeDescriptor) {
+        self.fields.insert(key, ty);
+    }
+
+    pub fn fields(&self) -> &BTreeMap<String, TypeDescriptor> {
+        &self.fields
+    }
+
+    /// Returns the type descriptor of a key.
+    pub fn get(&self, key: &str) -> Option<&TypeDescriptor> {
+        if let Some(ty) = self.fields.get(key) {
+            return Some(ty);
+        }
+        self.fields
+            .iter()
+            .find(|(pattern, _)| matches(pattern, key))
+            .map(|(_, ty)| ty)
+    }
+}
+
+fn matches(pattern: &str, key: &str) -> bool {
+    let mut pattern = pattern.split('.');
+    let mut key = key.split('.');
+    loop {
+        match (pattern.next(), key.next()) {
+            (Some(p), Some(k)) if p == "*" || p == k => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Types that can describe their tree encoding.
+pub trait TreeSchema {
+    fn describe_tree(schema: &mut Schema, prefix: &str);
+
+    fn schema() -> Schema {
+        let mut schema = Schema::new();
+        Self::describe_tree(&mut schema, "");
+        schema
+    }
+}
+
+impl<T: Describe> TreeSchema for T {
+    fn describe_tree(schema: &mut Schema, prefix: &str) {
+        schema.insert(prefix.to_string(), T::describe());
+    }
+}
+
+impl<T: TreeSchema> TreeSchema for TreeVec<T> {
+    fn describe_tree(schema: &mut Schema, prefix: &str) {
+        schema.insert(format!("{}.len", prefix), TypeDescriptor::U32);
+        T::describe_tree(schema, &format!("{}.*", prefix));
+    }
+}
+
+impl<K: Ord + Describe, V: TreeSchema> TreeSchema for TreeMap<K, V> {
+    fn describe_tree(schema: &mut Schema, prefix: &str) {
+        schema.insert(format!("{}.keys", prefix), Vec::<K>::describe());
+        V::describe_tree(schema, &format!("{}.*", prefix));
+    }
+}
+
+impl<T: TreeSchema> TreeSchema for TreeOption<T> {
+    fn describe_tree(schema: &mut Schema, prefix: &str) {
+        schema.insert(format!("{}.is_some", prefix), TypeDescriptor::Bool);
+        T::describe_tree(schema, &format!("{}.some", prefix));
+    }
+}
+
+/// Registry of named schemas.
+#[derive(Clone, Debug, Default)]
+pub struct SchemaRegistry {
+    schemas: BTreeMap<String, Schema>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register<T: TreeSchema>(&mut self, name: &str) {
+        self.schemas.insert(name.to_string(), T::schema());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Schema> {
+        self.schemas.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.schemas.keys().map(|name| name.as_str())
+    }
+}
+
+/// Renders an offchain block as json.
+///
+/// Keys are split on `.` into nested objects. Values without a matching field
+/// in the schema, or that fail to decode, are rendered as a cid if possible and
+/// as hex otherwise.
+pub fn render<H: Hasher>(block: &OffchainBlock<H>, schema: Option<&Schema>) -> Value
+where
+    H::Out: 'static,
+{
+    let mut root = Value::Object(Map::new());
+    for (key, bytes) in block.tree() {
+        let value = schema
+            .and_then(|schema| schema.get(key))
+            .and_then(|ty| {
+                let input = &mut &bytes[..];
+                let value = ty.decode(input).ok()?;
+                if input.is_empty() {
+                    Some(value)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_else(|| render_raw(bytes));
+        insert(&mut root, key, value);
+    }
+    root
+}
+
+fn render_raw(bytes: &[u8]) -> Value {
+    let input = &mut &bytes[..];
+    match <Cid as Decode>::decode(input) {
+        Ok(cid) if input.is_empty() => cid.to_string().into(),
+        _ => hex(bytes).into(),
+    }
+}
+
+fn insert(root: &mut Value, key: &str, value: Value) {
+    let mut node = root;
+    let mut segments = key.split('.').filter(|s| !s.is_empty()).peekable();
+    if segments.peek().is_none() {
+        *node = value;
+        return;
+    }
+    while let Some(segment) = segments.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let map = node.as_object_mut().expect("is object; qed");
+        if segments.peek().is_none() {
+            map.insert(segment.to_string(), value);
+            return;
+        }
+        node = map
+            .entry(segment)
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len() * 2 + 2);
+    res.push_str("0x");
+    for byte in bytes {
+        res.push_str(&format!("{:02x}", byte));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::TreeHasherBlake2b256 as TreeHasher;
+    use crate::trie::{BlockBuilder, PrefixIter, TreeEncode};
+    use parity_scale_codec::Encode;
+    use serde_json::json;
+
+    struct Block {
+        number: u32,
+        description: String,
+        items: TreeVec<u64>,
+    }
+
+    impl TreeEncode<TreeHasher> for Block {
+        fn encode_tree(&self, block: &mut BlockBuilder<TreeHasher>, prefix: &str, proof: bool) {
+            let mut prefixes = PrefixIter::new(prefix, &[".number", ".description", ".items"]);
+            self.number
+                .encode_tree(block, &prefixes.next().unwrap(), proof);
+            self.description
+                .encode_tree(block, &prefixes.next().unwrap(), proof);
+            self.items
+                .encode_tree(block, &prefixes.next().unwrap(), proof);
+        }
+    }
+
+    impl TreeSchema for Block {
+        fn describe_tree(schema: &mut Schema, prefix: &str) {
+            let mut prefixes = PrefixIter::new(prefix, &[".number", ".description", ".items"]);
+            u32::describe_tree(schema, &prefixes.next().unwrap());
+            String::describe_tree(schema, &prefixes.next().unwrap());
+            TreeVec::<u64>::describe_tree(schema, &prefixes.next().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let mut registry = SchemaRegistry::new();
+        registry.register::<Block>("block");
+
+        let block = Block {
+            number: 1,
+            description: "the first block".into(),
+            items: vec![42].into(),
+        };
+        let sealed = block.seal().unwrap();
+
+        let value = render(&sealed.offchain, registry.get("block"));
+        assert_eq!(
+            value,
+            json!({
+                "number": 1,
+                "description": "the first block",
+                "items": {
+                    "len": 1,
+                    "0": 42,
+                },
+            })
+        );
+
+        let value = render(&sealed.offchain, None);
+        assert_eq!(value["number"], json!("0x01000000"));
+    }
+
+    #[test]
+    fn test_decode_invalid_len() {
+        let ty = TypeDescriptor::Vec(Box::new(TypeDescriptor::U64));
+        let data = Compact(u32::MAX).encode();
+        assert!(ty.decode(&mut &data[..]).is_err());
+    }
+}
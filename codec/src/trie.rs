@@ -6,6 +6,7 @@ use sp_trie::{Layout, MemoryDB, TrieConfiguration, TrieDBMut, TrieHash, TrieMut}
 use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 use thiserror::Error;
 
 pub type VerifyError<H> = sp_trie::VerifyError<TrieHash<Layout<H>>, sp_trie::Error>;
@@ -197,6 +198,175 @@ where
     }
 }
 
+/// Vector spread across the tree.
+///
+/// The length is stored under `{prefix}.len` and every element under
+/// `{prefix}.{index}`, so each element can be proven individually.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeVec<T>(pub Vec<T>);
+
+impl<T> From<Vec<T>> for TreeVec<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self(vec)
+    }
+}
+
+impl<T> Deref for TreeVec<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for TreeVec<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: TreeEncode<H>, H: Hasher> TreeEncode<H> for TreeVec<T> {
+    fn encode_tree(&self, block: &mut BlockBuilder<H>, prefix: &str, proof: bool) {
+        block.insert(format!("{}.len", prefix), &(self.0.len() as u32), proof);
+        for (i, item) in self.0.iter().enumerate() {
+            item.encode_tree(block, &format!("{}.{}", prefix, i), proof);
+        }
+    }
+}
+
+impl<T: TreeDecode<H>, H: Hasher> TreeDecode<H> for TreeVec<T>
+where
+    H::Out: 'static,
+{
+    fn decode_tree(block: &OffchainBlock<H>, prefix: &str) -> Result<Self> {
+        let len: u32 = block.get(&format!("{}.len", prefix))?;
+        // the length isn't trusted, so the vec isn't preallocated.
+        let mut vec = Vec::new();
+        for i in 0..len {
+            vec.push(T::decode_tree(block, &format!("{}.{}", prefix, i))?);
+        }
+        Ok(Self(vec))
+    }
+}
+
+/// Map spread across the tree.
+///
+/// The keys are stored under `{prefix}.keys` and every value under
+/// `{prefix}.{key}` where `key` is the hex encoded scale encoding of the key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeMap<K: Ord, V>(pub BTreeMap<K, V>);
+
+impl<K: Ord + Encode, V> TreeMap<K, V> {
+    /// Returns the prefix a value is stored under.
+    pub fn key_prefix(prefix: &str, key: &K) -> String {
+        let bytes = key.encode();
+        let mut res = String::with_capacity(prefix.len() + 1 + bytes.len() * 2);
+        res.push_str(prefix);
+        res.push('.');
+        for byte in bytes {
+            res.push_str(&format!("{:02x}", byte));
+        }
+        res
+    }
+}
+
+impl<K: Ord, V> From<BTreeMap<K, V>> for TreeMap<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        Self(map)
+    }
+}
+
+impl<K: Ord, V> Deref for TreeMap<K, V> {
+    type Target = BTreeMap<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<K: Ord, V> DerefMut for TreeMap<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<K: Ord + Encode, V: TreeEncode<H>, H: Hasher> TreeEncode<H> for TreeMap<K, V> {
+    fn encode_tree(&self, block: &mut BlockBuilder<H>, prefix: &str, proof: bool) {
+        let keys: Vec<&K> = self.0.keys().collect();
+        block.insert(format!("{}.keys", prefix), &keys, proof);
+        for (k, v) in self.0.iter() {
+            v.encode_tree(block, &Self::key_prefix(prefix, k), proof);
+        }
+    }
+}
+
+impl<K: Ord + Encode + Decode, V: TreeDecode<H>, H: Hasher> TreeDecode<H> for TreeMap<K, V>
+where
+    H::Out: 'static,
+{
+    fn decode_tree(block: &OffchainBlock<H>, prefix: &str) -> Result<Self> {
+        let keys: Vec<K> = block.get(&format!("{}.keys", prefix))?;
+        let mut map = BTreeMap::new();
+        for k in keys {
+            let v = V::decode_tree(block, &Self::key_prefix(prefix, &k))?;
+            map.insert(k, v);
+        }
+        Ok(Self(map))
+    }
+}
+
+/// Option spread across the tree.
+///
+/// Stores a flag under `{prefix}.is_some` and the value under `{prefix}.some`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TreeOption<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for TreeOption<T> {
+    fn from(option: Option<T>) -> Self {
+        Self(option)
+    }
+}
+
+impl<T> Deref for TreeOption<T> {
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for TreeOption<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: TreeEncode<H>, H: Hasher> TreeEncode<H> for TreeOption<T> {
+    fn encode_tree(&self, block: &mut BlockBuilder<H>, prefix: &str, proof: bool) {
+        block.insert(format!("{}.is_some", prefix), &self.0.is_some(), proof);
+        if let Some(value) = self.0.as_ref() {
+            value.encode_tree(block, &format!("{}.some", prefix), proof);
+        }
+    }
+}
+
+impl<T: TreeDecode<H>, H: Hasher> TreeDecode<H> for TreeOption<T>
+where
+    H::Out: 'static,
+{
+    fn decode_tree(block: &OffchainBlock<H>, prefix: &str) -> Result<Self> {
+        let is_some: bool = block.get(&format!("{}.is_some", prefix))?;
+        if is_some {
+            Ok(Self(Some(T::decode_tree(
+                block,
+                &format!("{}.some", prefix),
+            )?)))
+        } else {
+            Ok(Self(None))
+        }
+    }
+}
+
 pub struct PrefixIter<'a> {
     prefix: &'a str,
     fields: std::slice::Iter<'a, &'a str>,
@@ -377,6 +547,78 @@ mod tests {
         assert_eq!(user, user2);
     }
 
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct Collections {
+        items: TreeVec<u32>,
+        map: TreeMap<u8, String>,
+        some: TreeOption<TreeVec<u64>>,
+        none: TreeOption<u64>,
+    }
+
+    impl TreeEncode<TreeHasher> for Collections {
+        fn encode_tree(&self, block: &mut BlockBuilder<TreeHasher>, prefix: &str, _proof: bool) {
+            let mut prefixes = PrefixIter::new(prefix, &[".items", ".map", ".some", ".none"]);
+            self.items
+                .encode_tree(block, &prefixes.next().unwrap(), true);
+            self.map
+                .encode_tree(block, &prefixes.next().unwrap(), false);
+            self.some
+                .encode_tree(block, &prefixes.next().unwrap(), false);
+            self.none
+                .encode_tree(block, &prefixes.next().unwrap(), false);
+        }
+    }
+
+    impl TreeDecode<TreeHasher> for Collections {
+        fn decode_tree(block: &OffchainBlock<TreeHasher>, prefix: &str) -> Result<Self> {
+            let mut prefixes = PrefixIter::new(prefix, &[".items", ".map", ".some", ".none"]);
+            let items = TreeDecode::decode_tree(block, &prefixes.next().unwrap())?;
+            let map = TreeDecode::decode_tree(block, &prefixes.next().unwrap())?;
+            let some = TreeDecode::decode_tree(block, &prefixes.next().unwrap())?;
+            let none = TreeDecode::decode_tree(block, &prefixes.next().unwrap())?;
+            Ok(Self {
+                items,
+                map,
+                some,
+                none,
+            })
+        }
+    }
+
+    #[test]
+    fn test_collections() {
+        let mut map = BTreeMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+        let value = Collections {
+            items: vec![42, 43].into(),
+            map: map.into(),
+            some: Some(TreeVec(vec![1, 2, 3])).into(),
+            none: None.into(),
+        };
+        let sealed = value.seal().unwrap();
+        sealed.verify_proof().unwrap();
+
+        let proven: Vec<&str> = sealed.proof_data.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(proven, vec![".items.0", ".items.1", ".items.len"]);
+
+        let tree = sealed.offchain.tree();
+        assert!(tree.contains_key(".map.01"));
+        assert!(tree.contains_key(".some.some.2"));
+        assert!(!tree.contains_key(".none.some"));
+
+        let value2 = Collections::decode(&sealed.offchain).unwrap();
+        assert_eq!(value, value2);
+    }
+
+    #[test]
+    fn test_tree_vec_invalid_len() {
+        let mut block = BlockBuilder::<TreeHasher>::new();
+        block.insert(".len".into(), &u32::MAX, false);
+        let sealed = block.seal().unwrap();
+        assert!(TreeVec::<u64>::decode(&sealed.offchain).is_err());
+    }
+
     #[test]
     fn test_trie() {
         let mut db = MemoryDB::default();
@@ -4,6 +4,7 @@ pub mod dh;
 pub mod error;
 pub mod keychain;
 pub mod keystore;
+pub mod metrics;
 pub mod rand;
 pub mod secret_box;
 pub mod secret_file;
@@ -0,0 +1,58 @@
+//! Prometheus metrics of the signers and secret boxes.
+//!
+//! Signers and secret boxes aren't owned by a client, so the metrics are
+//! process wide. They are only recorded after [`register_metrics`] was called,
+//! and are shared by all registries they are registered on.
+use once_cell::sync::OnceCell;
+use substrate_prometheus_endpoint::{
+    register, Counter, Histogram, HistogramOpts, PrometheusError, Registry, U64,
+};
+
+static METRICS: OnceCell<CryptoMetrics> = OnceCell::new();
+
+#[derive(Clone)]
+pub(crate) struct CryptoMetrics {
+    pub(crate) signatures: Counter<U64>,
+    pub(crate) secret_box_encrypt: Histogram,
+    pub(crate) secret_box_decrypt: Histogram,
+}
+
+impl CryptoMetrics {
+    fn new() -> Result<Self, PrometheusError> {
+        Ok(Self {
+            signatures: Counter::new(
+                "sunshine_signatures_total",
+                "Number of payloads and extrinsics signed",
+            )?,
+            secret_box_encrypt: Histogram::with_opts(HistogramOpts::new(
+                "sunshine_secret_box_encrypt_seconds",
+                "Time to encrypt a secret box",
+            ))?,
+            secret_box_decrypt: Histogram::with_opts(HistogramOpts::new(
+                "sunshine_secret_box_decrypt_seconds",
+                "Time to decrypt a secret box",
+            ))?,
+        })
+    }
+
+    fn register(&self, registry: &Registry) -> Result<(), PrometheusError> {
+        register(self.signatures.clone(), registry)?;
+        register(self.secret_box_encrypt.clone(), registry)?;
+        register(self.secret_box_decrypt.clone(), registry)?;
+        Ok(())
+    }
+}
+
+/// Registers the metrics of the signers and secret boxes on a registry.
+///
+/// The metrics are created on the first call, later calls register the same
+/// metrics on another registry.
+pub fn register_metrics(registry: &Registry) -> Result<(), PrometheusError> {
+    METRICS
+        .get_or_try_init(CryptoMetrics::new)?
+        .register(registry)
+}
+
+pub(crate) fn metrics() -> Option<&'static CryptoMetrics> {
+    METRICS.get()
+}
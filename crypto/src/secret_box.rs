@@ -1,5 +1,6 @@
 use crate::dh::DiffieHellman;
 use crate::keychain::{KeyChain, KeyType, TypedPair, TypedPublic};
+use crate::metrics::metrics;
 use parity_scale_codec::{Decode, Encode, Input};
 use rand::rngs::OsRng;
 use rand::RngCore;
@@ -68,6 +69,7 @@ impl<K: KeyType, T: Decode + Encode> SecretBox<K, T> {
         if recipients.len() as u8 as usize != recipients.len() {
             return Err(SecretBoxError::TooManyRecipients);
         }
+        let _timer = metrics().map(|m| m.secret_box_encrypt.start_timer());
         // Create a buffer.
         let capacity =
             recipients.len() * (X25519_LEN + TAG_LEN) + X25519_LEN + 1 + payload.size_hint();
@@ -119,6 +121,7 @@ impl<K: KeyType, T: Decode + Encode> SecretBox<K, T> {
     }
 
     pub fn decrypt(&self, key_chain: &KeyChain) -> Result<T, SecretBoxError> {
+        let _timer = metrics().map(|m| m.secret_box_decrypt.start_timer());
         let stream = &mut &self.secret[..];
 
         let mut len = [0];
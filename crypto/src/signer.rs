@@ -2,6 +2,7 @@ use crate::array::CryptoArray;
 use crate::dh::DiffieHellman;
 use crate::error::DiffieHellmanError;
 use crate::keychain::{KeyType, TypedPair};
+use crate::metrics::metrics;
 use generic_array::typenum::U32;
 use parity_scale_codec::Encode;
 use sp_core::Pair;
@@ -107,6 +108,9 @@ where
     }
 
     fn sign_extrinsic(&self, extrinsic: SignedPayload<T>) -> UncheckedExtrinsic<T> {
+        if let Some(metrics) = metrics() {
+            metrics.signatures.inc();
+        }
         let signature = extrinsic.using_encoded(|payload| self.signer.sign(payload));
         let (call, extra, _) = extrinsic.deconstruct();
         UncheckedExtrinsic::<T>::new_signed(
@@ -118,6 +122,9 @@ where
     }
 
     fn sign(&self, payload: &[u8]) -> T::Signature {
+        if let Some(metrics) = metrics() {
+            metrics.signatures.inc();
+        }
         self.signer.sign(payload).into()
     }
 
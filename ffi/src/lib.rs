@@ -1,4 +1,7 @@
-pub use {crate::error::LastError, allo_isolate, async_std, log, once_cell::sync::OnceCell};
+pub use {
+    crate::error::LastError, allo_isolate, async_std, log, once_cell::sync::OnceCell,
+    sunshine_client_utils::ClientConfig,
+};
 
 mod error;
 
@@ -144,6 +147,8 @@ macro_rules! gen_ffi {
 
         /// Setup the Sunshine Client using the provided path as the base path and with chainspec.
         ///
+        /// The network is configured from the config file in the base path.
+        ///
         /// ### Safety
         /// This assumes that the path non-null c string.
         /// chain_spec non-null c string.
@@ -153,6 +158,38 @@ macro_rules! gen_ffi {
             port: i64,
             path: *const ::std::os::raw::c_char,
             chain_spec: *const ::std::os::raw::c_char,
+        ) -> i32 {
+            let root = ::std::path::PathBuf::from(cstr!(path));
+            let chain_spec = ::std::path::PathBuf::from(cstr!(chain_spec));
+            __client_init(port, root, chain_spec, None)
+        }
+
+        /// Setup the Sunshine Client like `client_init`, with the network configured from
+        /// the toml file at the config path.
+        ///
+        /// ### Safety
+        /// This assumes that the path non-null c string.
+        /// chain_spec non-null c string.
+        /// config non-null c string.
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        #[no_mangle]
+        pub extern "C" fn client_init_with_config(
+            port: i64,
+            path: *const ::std::os::raw::c_char,
+            chain_spec: *const ::std::os::raw::c_char,
+            config: *const ::std::os::raw::c_char,
+        ) -> i32 {
+            let root = ::std::path::PathBuf::from(cstr!(path));
+            let chain_spec = ::std::path::PathBuf::from(cstr!(chain_spec));
+            let config = ::std::path::PathBuf::from(cstr!(config));
+            __client_init(port, root, chain_spec, Some(config))
+        }
+
+        fn __client_init(
+            port: i64,
+            root: ::std::path::PathBuf,
+            chain_spec: ::std::path::PathBuf,
+            config: Option<::std::path::PathBuf>,
         ) -> i32 {
             // check if we already created the client, and return `0xdead >> 0x01`
             // if it is already created to avoid any unwanted work
@@ -161,11 +198,14 @@ macro_rules! gen_ffi {
             }
             /// Setup a panic hook with the logger.
             ::std::env::set_var("RUST_BACKTRACE", "full");
-            let root = ::std::path::PathBuf::from(cstr!(path));
-            let chain_spec = ::std::path::PathBuf::from(cstr!(chain_spec));
             let isolate = $crate::allo_isolate::Isolate::new(port);
             let t = isolate.task(async move {
-                let client = <$c>::new(&root, &chain_spec).await;
+                let config = match config {
+                    Some(config) => $crate::ClientConfig::from_file(&config),
+                    None => $crate::ClientConfig::load(&root),
+                };
+                let config = $crate::result!(config, 0xdead >> 0x03);
+                let client = <$c>::new_with_config(&root, &chain_spec, &config).await;
                 let client = $crate::result!(client, 0xdead >> 0x02);
                 $crate::result!(CLIENT.set(RwLock::new(client)).map_err(|_| ()), 0xdead >> 0x01);
                 1
@@ -0,0 +1,86 @@
+use ipfs_embed_core::{Cid, PeerId};
+use std::collections::{HashMap, HashSet};
+use sunshine_codec::codec::SECRET;
+
+/// Restricts encrypted blocks to the peers of a group.
+///
+/// Blocks that aren't encrypted are public. Encrypted blocks that weren't
+/// assigned to a group aren't sent to any peer.
+#[derive(Default)]
+pub(crate) struct AccessControl {
+    blocks: HashMap<Cid, u8>,
+    groups: HashMap<u8, HashSet<PeerId>>,
+}
+
+impl AccessControl {
+    pub fn protect(&mut self, cid: Cid, group: u8) {
+        self.blocks.insert(cid, group);
+    }
+
+    pub fn authorize(&mut self, peer_id: PeerId, group: u8) {
+        self.groups.entry(group).or_default().insert(peer_id);
+    }
+
+    pub fn revoke(&mut self, peer_id: &PeerId, group: u8) {
+        if let Some(peers) = self.groups.get_mut(&group) {
+            peers.remove(peer_id);
+        }
+    }
+
+    pub fn is_public(cid: &Cid) -> bool {
+        cid.codec() != SECRET
+    }
+
+    pub fn is_authorized(&self, peer_id: &PeerId, cid: &Cid) -> bool {
+        Self::is_public(cid) || self.authorized(cid).contains(peer_id)
+    }
+
+    /// Returns the peers authorized to receive an encrypted block.
+    pub fn authorized(&self, cid: &Cid) -> HashSet<PeerId> {
+        self.blocks
+            .get(cid)
+            .and_then(|group| self.groups.get(group))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::block::Block;
+    use libipld::multihash::SHA2_256;
+    use libipld::raw::RawCodec;
+    use libipld::store::DefaultStoreParams;
+    use sunshine_codec::codec::{Secret, SecretCodec};
+    use sunshine_codec::hasher::{Multihash, BLAKE2B_256};
+    use sunshine_codec::Multicodec;
+
+    #[derive(Clone)]
+    struct Params;
+
+    impl libipld::store::StoreParams for Params {
+        type Hashes = Multihash;
+        type Codecs = Multicodec;
+        const MAX_BLOCK_SIZE: usize = u16::MAX as _;
+    }
+
+    #[test]
+    fn test_access_control() {
+        let public = Block::<DefaultStoreParams>::encode(RawCodec, SHA2_256, b"public").unwrap();
+        let secret = Secret(b"secret".to_vec());
+        let secret = Block::<Params>::encode(SecretCodec, BLAKE2B_256, &secret).unwrap();
+        let peer = PeerId::random();
+
+        let mut access = AccessControl::default();
+        assert!(access.is_authorized(&peer, public.cid()));
+        assert!(!access.is_authorized(&peer, secret.cid()));
+
+        access.protect(*secret.cid(), 1);
+        access.authorize(peer.clone(), 1);
+        assert!(access.is_authorized(&peer, secret.cid()));
+
+        access.revoke(&peer, 1);
+        assert!(!access.is_authorized(&peer, secret.cid()));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::peers::Peers;
+use async_std::task;
+use ipfs_embed_core::{PeerId, StoreParams};
+use sc_network::{ExHashT, NetworkService};
+use sp_runtime::traits::Block;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// Dials peers by adding them as reserved peers.
+///
+/// Reserved peers are kept connected by substrate, so they are removed again
+/// when they disconnect or when no wants are outstanding.
+pub(crate) struct Dialer<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> {
+    _marker: PhantomData<S>,
+    net: Option<Arc<NetworkService<B, H, S::Hashes>>>,
+    peers: Arc<Mutex<Peers>>,
+}
+
+impl<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> Clone for Dialer<B, H, S> {
+    fn clone(&self) -> Self {
+        Self {
+            _marker: PhantomData,
+            net: self.net.clone(),
+            peers: self.peers.clone(),
+        }
+    }
+}
+
+impl<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> Dialer<B, H, S> {
+    pub fn new(
+        net: Option<Arc<NetworkService<B, H, S::Hashes>>>,
+        peers: Arc<Mutex<Peers>>,
+    ) -> Self {
+        Self {
+            _marker: PhantomData,
+            net,
+            peers,
+        }
+    }
+
+    /// Dials a peer unless it is banned or already dialed.
+    ///
+    /// The addresses of the peer learned by kademlia and identify are looked
+    /// up in the network state and added to the known addresses first.
+    pub fn dial(&self, peer_id: PeerId) {
+        let net = if let Some(net) = &self.net {
+            net.clone()
+        } else {
+            return;
+        };
+        {
+            let mut peers = self.peers.lock().unwrap();
+            if peers.is_banned(&peer_id) || !peers.reserve(&peer_id) {
+                return;
+            }
+        }
+        let peers = self.peers.clone();
+        task::spawn(async move {
+            match net.network_state().await {
+                Ok(state) => {
+                    let key = peer_id.to_base58();
+                    let addresses = if let Some(peer) = state.connected_peers.get(&key) {
+                        peer.known_addresses.clone()
+                    } else if let Some(peer) = state.not_connected_peers.get(&key) {
+                        peer.known_addresses.clone()
+                    } else {
+                        Default::default()
+                    };
+                    let mut peers = peers.lock().unwrap();
+                    for addr in addresses {
+                        peers.add_address(&peer_id, addr);
+                    }
+                }
+                Err(_) => log::debug!("failed to query the network state"),
+            }
+            let addresses = peers.lock().unwrap().addresses(&peer_id);
+            let mut dialed = false;
+            for addr in addresses {
+                match net.add_reserved_peer(format!("{}/p2p/{}", addr, peer_id)) {
+                    Ok(()) => dialed = true,
+                    Err(err) => log::warn!("failed to dial {} at {}: {}", peer_id, addr, err),
+                }
+            }
+            if !dialed {
+                log::debug!("no address of {} to dial", peer_id);
+                peers.lock().unwrap().unreserve(&peer_id);
+            }
+        });
+    }
+
+    /// Removes a disconnected peer from the reserved peers.
+    pub fn disconnected(&self, peer_id: &PeerId) {
+        if !self.peers.lock().unwrap().unreserve(peer_id) {
+            return;
+        }
+        if let Some(net) = &self.net {
+            net.remove_reserved_peer(peer_id.clone());
+        }
+    }
+
+    /// Removes all dialed peers from the reserved peers.
+    pub fn release(&self) {
+        let reserved = self.peers.lock().unwrap().take_reserved();
+        if let Some(net) = &self.net {
+            for peer_id in reserved {
+                net.remove_reserved_peer(peer_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use ipfs_embed_core::{Cid, NetworkEvent, PeerId, Stream};
+use sc_network::{BitswapEvent, DhtEvent, Event, Key};
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Event of the substrate network.
+#[derive(Debug)]
+pub enum SubstrateEvent {
+    /// Dht or bitswap event used by the offchain store.
+    Offchain(NetworkEvent),
+    /// Opened a sync substream with a peer.
+    PeerConnected(PeerId),
+    /// Closed the sync substream with a peer.
+    PeerDisconnected(PeerId),
+    /// Dht event for a key that isn't a valid cid.
+    InvalidCid(Key, String),
+    /// Notification or dht record event.
+    Other(Event),
+}
+
+fn offchain_event(key: Key, f: impl FnOnce(Cid) -> NetworkEvent) -> SubstrateEvent {
+    match Cid::try_from(key.as_ref()) {
+        Ok(cid) => SubstrateEvent::Offchain(f(cid)),
+        Err(err) => SubstrateEvent::InvalidCid(key, err.to_string()),
+    }
+}
+
+impl From<Event> for SubstrateEvent {
+    fn from(ev: Event) -> Self {
+        match ev {
+            Event::Dht(DhtEvent::Providers(key, providers)) => {
+                offchain_event(key, |cid| NetworkEvent::Providers(cid, providers))
+            }
+            Event::Dht(DhtEvent::GetProvidersFailed(key)) => {
+                offchain_event(key, NetworkEvent::GetProvidersFailed)
+            }
+            Event::Dht(DhtEvent::Providing(key)) => offchain_event(key, NetworkEvent::Providing),
+            Event::Dht(DhtEvent::StartProvidingFailed(key)) => {
+                offchain_event(key, NetworkEvent::StartProvidingFailed)
+            }
+            Event::Dht(DhtEvent::BootstrapComplete) => {
+                SubstrateEvent::Offchain(NetworkEvent::BootstrapComplete)
+            }
+            Event::Bitswap(BitswapEvent::ReceivedBlock(peer_id, cid, data)) => {
+                SubstrateEvent::Offchain(NetworkEvent::ReceivedBlock(peer_id, cid, data.to_vec()))
+            }
+            Event::Bitswap(BitswapEvent::ReceivedWant(peer_id, cid, priority)) => {
+                SubstrateEvent::Offchain(NetworkEvent::ReceivedWant(peer_id, cid, priority))
+            }
+            Event::SyncConnected { remote } => SubstrateEvent::PeerConnected(remote),
+            Event::SyncDisconnected { remote } => SubstrateEvent::PeerDisconnected(remote),
+            ev => SubstrateEvent::Other(ev),
+        }
+    }
+}
+
+/// Subscription to all events of the substrate network.
+pub struct EventSubscription {
+    pub(crate) events: Box<dyn Stream<Item = Event> + Send + Unpin>,
+}
+
+impl Stream for EventSubscription {
+    type Item = SubstrateEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.events)
+            .poll_next(cx)
+            .map(|ev| ev.map(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_cid() {
+        let key = Key::new(&b"invalid");
+        let ev = SubstrateEvent::from(Event::Dht(DhtEvent::Providing(key)));
+        assert!(matches!(ev, SubstrateEvent::InvalidCid(_, _)));
+
+        let remote = PeerId::random();
+        let ev = SubstrateEvent::from(Event::SyncConnected {
+            remote: remote.clone(),
+        });
+        assert!(matches!(ev, SubstrateEvent::PeerConnected(peer_id) if peer_id == remote));
+    }
+}
@@ -1,89 +1,348 @@
 use ipfs_embed_core::{Cid, Multiaddr, Network, NetworkEvent, PeerId, StoreParams, Stream};
 pub use sc_network;
-use sc_network::{BitswapEvent, DhtEvent, Event, ExHashT, Key, NetworkService, NetworkStateInfo};
+use sc_network::{Event, ExHashT, Key, NetworkService, NetworkStateInfo, ReputationChange};
 use sp_runtime::traits::Block;
-use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Instant;
+
+mod access;
+mod dial;
+mod events;
+mod metrics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod peers;
+mod session;
+
+use access::AccessControl;
+use dial::Dialer;
+pub use events::{EventSubscription, SubstrateEvent};
+pub use metrics::NetworkMetrics;
+pub use peers::PeerInfo;
+use peers::Peers;
+pub use session::{PeerScore, Session, SessionClosed, SessionConfig, SessionTimeout};
+
+/// Counters of the offchain network.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NetworkStats {
+    /// Number of wanted blocks that weren't received or cancelled yet.
+    pub outstanding_wants: usize,
+    /// Number of blocks received from peers.
+    pub received_blocks: u64,
+    /// Number of bytes received from peers.
+    pub received_bytes: u64,
+    /// Number of wants received from peers.
+    pub received_wants: u64,
+    /// Number of blocks sent to peers.
+    pub sent_blocks: u64,
+    /// Number of bytes sent to peers.
+    pub sent_bytes: u64,
+    /// Number of cids provided in the dht.
+    pub providing: usize,
+    /// Number of provider queries that didn't complete yet.
+    pub pending_provider_queries: usize,
+    /// Number of provider queries that failed.
+    pub failed_provider_queries: u64,
+    /// Number of dht events for keys that aren't valid cids.
+    pub invalid_cids: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    stats: NetworkStats,
+    metrics: Option<NetworkMetrics>,
+    wants: HashMap<Cid, Instant>,
+    providing: HashSet<Cid>,
+    queries: HashSet<Cid>,
+}
+
+impl Counters {
+    fn record_sent(&mut self, len: usize) {
+        self.stats.sent_blocks += 1;
+        self.stats.sent_bytes += len as u64;
+    }
+
+    fn record(&mut self, ev: &NetworkEvent) {
+        match ev {
+            NetworkEvent::Providers(cid, _) => {
+                self.queries.remove(cid);
+            }
+            NetworkEvent::GetProvidersFailed(cid) => {
+                self.queries.remove(cid);
+                self.stats.failed_provider_queries += 1;
+            }
+            NetworkEvent::StartProvidingFailed(cid) => {
+                self.providing.remove(cid);
+            }
+            NetworkEvent::ReceivedBlock(_, cid, data) => {
+                if let Some(start) = self.wants.remove(cid) {
+                    if let Some(metrics) = &self.metrics {
+                        metrics.want_latency.observe(start.elapsed().as_secs_f64());
+                    }
+                }
+                self.stats.received_blocks += 1;
+                self.stats.received_bytes += data.len() as u64;
+            }
+            NetworkEvent::ReceivedWant(_, _, _) => {
+                self.stats.received_wants += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn snapshot(&self) -> NetworkStats {
+        NetworkStats {
+            outstanding_wants: self.wants.len(),
+            providing: self.providing.len(),
+            pending_provider_queries: self.queries.len(),
+            ..self.stats.clone()
+        }
+    }
+}
 
 pub struct SubstrateNetwork<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> {
     _marker: PhantomData<S>,
-    net: Arc<NetworkService<B, H, S::Hashes>>,
+    local_peer_id: PeerId,
+    net: Option<Arc<NetworkService<B, H, S::Hashes>>>,
+    counters: Arc<Mutex<Counters>>,
+    peers: Arc<Mutex<Peers>>,
+    access: Arc<Mutex<AccessControl>>,
+    dialer: Dialer<B, H, S>,
 }
 
 impl<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> SubstrateNetwork<B, H, S> {
     pub fn new(net: Arc<NetworkService<B, H, S::Hashes>>) -> Self {
+        Self::with_service(net.local_peer_id().clone(), Some(net))
+    }
+
+    /// Creates a network that isn't connected to any peers.
+    ///
+    /// Blocks are only served from the local store.
+    pub fn offline() -> Self {
+        Self::with_service(PeerId::random(), None)
+    }
+
+    fn with_service(
+        local_peer_id: PeerId,
+        net: Option<Arc<NetworkService<B, H, S::Hashes>>>,
+    ) -> Self {
+        let peers = Arc::new(Mutex::new(Peers::default()));
         Self {
             _marker: PhantomData,
+            local_peer_id,
+            dialer: Dialer::new(net.clone(), peers.clone()),
             net,
+            counters: Default::default(),
+            peers,
+            access: Default::default(),
+        }
+    }
+
+    /// Returns true if the network isn't connected to a substrate network.
+    pub fn is_offline(&self) -> bool {
+        self.net.is_none()
+    }
+
+    fn event_stream(&self, name: &'static str) -> Box<dyn Stream<Item = Event> + Send + Unpin> {
+        if let Some(net) = &self.net {
+            Box::new(net.event_stream(name))
+        } else {
+            Box::new(futures::stream::pending())
         }
     }
+
+    /// Subscribes to all events of the network.
+    ///
+    /// Unlike the subscription used by the offchain store, this includes sync,
+    /// notification and peer events and reports dht keys that aren't valid
+    /// cids.
+    pub fn subscribe_events(&self) -> EventSubscription {
+        EventSubscription {
+            events: self.event_stream("sunshine-events"),
+        }
+    }
+
+    /// Returns all known peers, connected peers first and ordered by the
+    /// number of blocks they served.
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        self.peers.lock().unwrap().list()
+    }
+
+    /// Adds an address used by `connect` to dial the peer.
+    pub fn add_address(&self, peer_id: &PeerId, addr: Multiaddr) {
+        self.peers.lock().unwrap().add_address(peer_id, addr);
+    }
+
+    /// Bans a peer.
+    ///
+    /// The peer is disconnected and its blocks, wants and provider records
+    /// are ignored until it is unbanned.
+    pub fn ban(&self, peer_id: &PeerId) {
+        let mut peers = self.peers.lock().unwrap();
+        peers.ban(peer_id);
+        peers.unreserve(peer_id);
+        drop(peers);
+        if let Some(net) = &self.net {
+            net.remove_reserved_peer(peer_id.clone());
+            net.report_peer(peer_id.clone(), ReputationChange::new_fatal("banned"));
+        }
+    }
+
+    /// Unbans a peer.
+    pub fn unban(&self, peer_id: &PeerId) {
+        self.peers.lock().unwrap().unban(peer_id);
+    }
+
+    /// Restricts an encrypted block to the peers of `group`.
+    ///
+    /// Wants for encrypted blocks that weren't restricted to a group are
+    /// ignored.
+    pub fn protect(&self, cid: Cid, group: u8) {
+        self.access.lock().unwrap().protect(cid, group);
+    }
+
+    /// Authorizes a peer to receive the encrypted blocks of `group`.
+    pub fn authorize(&self, peer_id: PeerId, group: u8) {
+        self.access.lock().unwrap().authorize(peer_id, group);
+    }
+
+    /// Revokes the authorization of a peer to receive the encrypted blocks of
+    /// `group`.
+    pub fn revoke(&self, peer_id: &PeerId, group: u8) {
+        self.access.lock().unwrap().revoke(peer_id, group);
+    }
+
+    /// Returns the network counters.
+    pub fn stats(&self) -> NetworkStats {
+        self.counters.lock().unwrap().snapshot()
+    }
+
+    /// Sets the prometheus metrics recorded by the network.
+    pub fn set_metrics(&self, metrics: NetworkMetrics) {
+        self.counters.lock().unwrap().metrics = Some(metrics);
+    }
 }
 
 impl<B: Block + 'static, H: ExHashT, S: StoreParams + Unpin + 'static> Network<S>
     for SubstrateNetwork<B, H, S>
 {
-    type Subscription = Subscription;
+    type Subscription = Subscription<B, H, S>;
 
     fn local_peer_id(&self) -> &PeerId {
-        self.net.local_peer_id()
+        &self.local_peer_id
     }
 
     fn external_addresses(&self) -> Vec<Multiaddr> {
-        self.net.external_addresses()
+        if let Some(net) = &self.net {
+            net.external_addresses()
+        } else {
+            Default::default()
+        }
     }
 
     fn provide(&self, cid: &Cid) {
+        self.counters.lock().unwrap().providing.insert(*cid);
         let key = Key::new(&cid.to_bytes());
-        self.net.provide(key);
+        if let Some(net) = &self.net {
+            net.provide(key);
+        }
     }
 
     fn unprovide(&self, cid: &Cid) {
+        self.counters.lock().unwrap().providing.remove(cid);
         let key = Key::new(&cid.to_bytes());
-        self.net.unprovide(key);
+        if let Some(net) = &self.net {
+            net.unprovide(key);
+        }
     }
 
     fn providers(&self, cid: &Cid) {
+        self.counters.lock().unwrap().queries.insert(*cid);
         let key = Key::new(&cid.to_bytes());
-        self.net.providers(key);
+        if let Some(net) = &self.net {
+            net.providers(key);
+        }
     }
 
-    fn connect(&self, _peer_id: PeerId) {
-        // TODO
+    fn connect(&self, peer_id: PeerId) {
+        self.dialer.dial(peer_id);
     }
 
     fn want(&self, cid: Cid, priority: i32) {
-        self.net.bitswap_want_block(cid, priority)
+        self.counters
+            .lock()
+            .unwrap()
+            .wants
+            .entry(cid)
+            .or_insert_with(Instant::now);
+        if let Some(net) = &self.net {
+            net.bitswap_want_block(cid, priority)
+        }
     }
 
     fn cancel(&self, cid: Cid) {
-        self.net.bitswap_cancel_block(cid)
+        let mut counters = self.counters.lock().unwrap();
+        counters.wants.remove(&cid);
+        let done = counters.wants.is_empty();
+        drop(counters);
+        if done {
+            self.dialer.release();
+        }
+        if let Some(net) = &self.net {
+            net.bitswap_cancel_block(cid)
+        }
     }
 
     fn send_to(&self, peer_id: PeerId, cid: Cid, data: Vec<u8>) {
-        self.net
-            .bitswap_send_block(peer_id, cid, data.into_boxed_slice())
+        if !self.access.lock().unwrap().is_authorized(&peer_id, &cid) {
+            return;
+        }
+        if let Some(net) = &self.net {
+            self.counters.lock().unwrap().record_sent(data.len());
+            net.bitswap_send_block(peer_id, cid, data.into_boxed_slice())
+        }
     }
 
     fn send(&self, cid: Cid, data: Vec<u8>) {
-        self.net
-            .bitswap_send_block_all(cid, data.into_boxed_slice())
+        if !AccessControl::is_public(&cid) {
+            let peers = self.access.lock().unwrap().authorized(&cid);
+            for peer_id in peers {
+                self.send_to(peer_id, cid, data.clone());
+            }
+            return;
+        }
+        if let Some(net) = &self.net {
+            self.counters.lock().unwrap().record_sent(data.len());
+            net.bitswap_send_block_all(cid, data.into_boxed_slice())
+        }
     }
 
     fn subscribe(&self) -> Self::Subscription {
         Subscription {
-            events: Box::new(self.net.event_stream("ipfs-embed")),
+            events: self.event_stream("ipfs-embed"),
+            counters: self.counters.clone(),
+            peers: self.peers.clone(),
+            access: self.access.clone(),
+            dialer: self.dialer.clone(),
         }
     }
 }
 
-pub struct Subscription {
+pub struct Subscription<B: Block + 'static, H: ExHashT, S: StoreParams + 'static> {
     events: Box<dyn Stream<Item = Event> + Send + Unpin>,
+    counters: Arc<Mutex<Counters>>,
+    peers: Arc<Mutex<Peers>>,
+    access: Arc<Mutex<AccessControl>>,
+    dialer: Dialer<B, H, S>,
 }
 
-impl Stream for Subscription {
+impl<B: Block + 'static, H: ExHashT, S: StoreParams + Unpin + 'static> Stream
+    for Subscription<B, H, S>
+{
     type Item = NetworkEvent;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
@@ -93,44 +352,48 @@ impl Stream for Subscription {
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(None) => return Poll::Ready(None),
             };
-            let ev = match ev {
-                Event::Dht(DhtEvent::Providers(key, providers)) => {
-                    if let Ok(cid) = Cid::try_from(key.as_ref()) {
-                        NetworkEvent::Providers(cid, providers)
-                    } else {
-                        continue;
-                    }
-                }
-                Event::Dht(DhtEvent::GetProvidersFailed(key)) => {
-                    if let Ok(cid) = Cid::try_from(key.as_ref()) {
-                        NetworkEvent::GetProvidersFailed(cid)
-                    } else {
-                        continue;
-                    }
+            let ev = match SubstrateEvent::from(ev) {
+                SubstrateEvent::Offchain(ev) => ev,
+                SubstrateEvent::PeerConnected(peer_id) => {
+                    self.peers.lock().unwrap().connected(&peer_id);
+                    continue;
                 }
-                Event::Dht(DhtEvent::Providing(key)) => {
-                    if let Ok(cid) = Cid::try_from(key.as_ref()) {
-                        NetworkEvent::Providing(cid)
-                    } else {
-                        continue;
-                    }
+                SubstrateEvent::PeerDisconnected(peer_id) => {
+                    self.peers.lock().unwrap().disconnected(&peer_id);
+                    self.dialer.disconnected(&peer_id);
+                    continue;
                 }
-                Event::Dht(DhtEvent::StartProvidingFailed(key)) => {
-                    if let Ok(cid) = Cid::try_from(key.as_ref()) {
-                        NetworkEvent::StartProvidingFailed(cid)
-                    } else {
-                        continue;
-                    }
+                SubstrateEvent::InvalidCid(_, _) => {
+                    self.counters.lock().unwrap().stats.invalid_cids += 1;
+                    continue;
                 }
-                Event::Dht(DhtEvent::BootstrapComplete) => NetworkEvent::BootstrapComplete,
-                Event::Bitswap(BitswapEvent::ReceivedBlock(peer_id, cid, data)) => {
-                    NetworkEvent::ReceivedBlock(peer_id, cid, data.to_vec())
+                SubstrateEvent::Other(_) => continue,
+            };
+            if let NetworkEvent::ReceivedWant(peer_id, cid, _) = &ev {
+                if !self.access.lock().unwrap().is_authorized(peer_id, cid) {
+                    continue;
                 }
-                Event::Bitswap(BitswapEvent::ReceivedWant(peer_id, cid, priority)) => {
-                    NetworkEvent::ReceivedWant(peer_id, cid, priority)
+            }
+            let ev = if let Some(ev) = self.peers.lock().unwrap().filter(ev) {
+                ev
+            } else {
+                continue;
+            };
+            if let NetworkEvent::Providers(_, providers) = &ev {
+                // dial the peers that served the most blocks first.
+                let ranked = self.peers.lock().unwrap().rank(providers.iter().cloned());
+                for peer_id in ranked {
+                    self.dialer.dial(peer_id);
                 }
-                _ => continue,
+            }
+            let done = {
+                let mut counters = self.counters.lock().unwrap();
+                counters.record(&ev);
+                matches!(ev, NetworkEvent::ReceivedBlock(_, _, _)) && counters.wants.is_empty()
             };
+            if done {
+                self.dialer.release();
+            }
             return Poll::Ready(Some(ev));
         }
     }
@@ -224,6 +487,21 @@ mod tests {
         assert_eq!(block.data(), block2.data());
     }
 
+    #[async_std::test]
+    async fn test_offline_store() {
+        let sled_config = sled::Config::new().temporary(true);
+        let storage =
+            Arc::new(StorageService::open(&sled_config, 10, Duration::from_secs(10)).unwrap());
+        let network = Arc::new(Network::offline());
+        let store = Ipfs::new(storage, network, Duration::from_millis(100));
+        let block = create_block(b"test_offline_store");
+        store.insert(&block).await.unwrap();
+        let block2 = store.get(block.cid()).await.unwrap();
+        assert_eq!(block.data(), block2.data());
+        let missing = create_block(b"test_offline_store_missing");
+        assert!(store.get(missing.cid()).await.is_err());
+    }
+
     #[async_std::test]
     #[cfg(not(target_os = "macos"))] // mdns doesn't work on macos in github actions
     async fn test_exchange_mdns() {
@@ -276,6 +554,31 @@ mod tests {
         assert_eq!(block.data(), block2.data());
     }
 
+    #[test]
+    fn test_counters() {
+        let registry = substrate_prometheus_endpoint::Registry::new();
+        let mut counters = Counters::default();
+        counters.metrics = Some(NetworkMetrics::register(&registry).unwrap());
+        let cid = *create_block(b"test_counters").cid();
+        counters.wants.insert(cid, Instant::now());
+        counters.queries.insert(cid);
+        assert_eq!(counters.snapshot().outstanding_wants, 1);
+        counters.record(&NetworkEvent::GetProvidersFailed(cid));
+        counters.record(&NetworkEvent::ReceivedBlock(
+            PeerId::random(),
+            cid,
+            vec![0; 4],
+        ));
+        let stats = counters.snapshot();
+        assert_eq!(stats.outstanding_wants, 0);
+        assert_eq!(stats.pending_provider_queries, 0);
+        assert_eq!(stats.failed_provider_queries, 1);
+        assert_eq!(stats.received_blocks, 1);
+        assert_eq!(stats.received_bytes, 4);
+        let metrics = counters.metrics.unwrap();
+        assert_eq!(metrics.want_latency.get_sample_count(), 1);
+    }
+
     #[async_std::test]
     async fn test_provider_not_found() {
         env_logger::try_init().ok();
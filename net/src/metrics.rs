@@ -0,0 +1,26 @@
+use substrate_prometheus_endpoint::{
+    exponential_buckets, register, Histogram, HistogramOpts, PrometheusError, Registry,
+};
+
+/// Prometheus metrics of the offchain network.
+#[derive(Clone)]
+pub struct NetworkMetrics {
+    pub(crate) want_latency: Histogram,
+}
+
+impl NetworkMetrics {
+    pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Self {
+            want_latency: register(
+                Histogram::with_opts(
+                    HistogramOpts::new(
+                        "sunshine_bitswap_want_latency_seconds",
+                        "Time until a wanted block is received",
+                    )
+                    .buckets(exponential_buckets(0.01, 2.0, 12)?),
+                )?,
+                registry,
+            )?,
+        })
+    }
+}
@@ -0,0 +1,314 @@
+//! In-memory network for testing the offchain store.
+use async_std::task;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use ipfs_embed_core::{Cid, Multiaddr, Network, NetworkEvent, PeerId, StoreParams, Stream};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Conditions of the simulated network.
+#[derive(Clone, Debug)]
+pub struct MockConfig {
+    /// Delay of every message.
+    pub latency: Duration,
+    /// Probability of dropping a message.
+    pub loss: f64,
+    /// Seed of the generator deciding which messages are dropped.
+    pub seed: u64,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(0),
+            loss: 0.0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    subscribers: HashMap<PeerId, Vec<UnboundedSender<NetworkEvent>>>,
+    providers: HashMap<Cid, HashSet<PeerId>>,
+    rng: u64,
+}
+
+impl State {
+    /// Xorshift generator, so that dropped messages are reproducible.
+    fn sample(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Network connecting all `MockNetwork`s created from it.
+#[derive(Clone)]
+pub struct MockHub {
+    config: MockConfig,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockHub {
+    pub fn new(config: MockConfig) -> Self {
+        let state = State {
+            rng: config.seed.max(1),
+            ..Default::default()
+        };
+        Self {
+            config,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Creates a new peer.
+    pub fn network<S: StoreParams>(&self) -> MockNetwork<S> {
+        let peer_id = PeerId::random();
+        self.state
+            .lock()
+            .unwrap()
+            .subscribers
+            .insert(peer_id.clone(), Default::default());
+        MockNetwork {
+            _marker: PhantomData,
+            peer_id,
+            hub: self.clone(),
+        }
+    }
+
+    fn deliver(&self, to: &PeerId, ev: NetworkEvent) {
+        let mut state = self.state.lock().unwrap();
+        if self.config.loss > 0.0 && state.sample() < self.config.loss {
+            return;
+        }
+        let subscribers = if let Some(subscribers) = state.subscribers.get_mut(to) {
+            subscribers
+        } else {
+            return;
+        };
+        subscribers.retain(|tx| !tx.is_closed());
+        let subscribers = subscribers.clone();
+        drop(state);
+
+        let latency = self.config.latency;
+        let send = move || {
+            for tx in subscribers {
+                tx.unbounded_send(ev.clone()).ok();
+            }
+        };
+        if latency == Duration::from_millis(0) {
+            send();
+        } else {
+            task::spawn(async move {
+                task::sleep(latency).await;
+                send();
+            });
+        }
+    }
+
+    fn peers(&self, except: &PeerId) -> Vec<PeerId> {
+        self.state
+            .lock()
+            .unwrap()
+            .subscribers
+            .keys()
+            .filter(|peer_id| *peer_id != except)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Peer of a `MockHub`.
+///
+/// Every peer is connected to every other peer of the hub. Wants and blocks
+/// sent with `send` are broadcast to all peers, provider records are shared
+/// by the hub.
+pub struct MockNetwork<S> {
+    _marker: PhantomData<S>,
+    peer_id: PeerId,
+    hub: MockHub,
+}
+
+impl<S: StoreParams + Unpin + 'static> Network<S> for MockNetwork<S> {
+    type Subscription = MockSubscription;
+
+    fn local_peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    fn external_addresses(&self) -> Vec<Multiaddr> {
+        Default::default()
+    }
+
+    fn provide(&self, cid: &Cid) {
+        self.hub
+            .state
+            .lock()
+            .unwrap()
+            .providers
+            .entry(*cid)
+            .or_default()
+            .insert(self.peer_id.clone());
+        self.hub
+            .deliver(&self.peer_id, NetworkEvent::Providing(*cid));
+    }
+
+    fn unprovide(&self, cid: &Cid) {
+        if let Some(providers) = self.hub.state.lock().unwrap().providers.get_mut(cid) {
+            providers.remove(&self.peer_id);
+        }
+    }
+
+    fn providers(&self, cid: &Cid) {
+        let providers = self
+            .hub
+            .state
+            .lock()
+            .unwrap()
+            .providers
+            .get(cid)
+            .cloned()
+            .unwrap_or_default();
+        let ev = if providers.is_empty() {
+            NetworkEvent::GetProvidersFailed(*cid)
+        } else {
+            NetworkEvent::Providers(*cid, providers.into_iter().collect())
+        };
+        self.hub.deliver(&self.peer_id, ev);
+    }
+
+    fn connect(&self, _peer_id: PeerId) {}
+
+    fn want(&self, cid: Cid, priority: i32) {
+        for peer_id in self.hub.peers(&self.peer_id) {
+            let ev = NetworkEvent::ReceivedWant(self.peer_id.clone(), cid, priority);
+            self.hub.deliver(&peer_id, ev);
+        }
+    }
+
+    fn cancel(&self, _cid: Cid) {}
+
+    fn send_to(&self, peer_id: PeerId, cid: Cid, data: Vec<u8>) {
+        let ev = NetworkEvent::ReceivedBlock(self.peer_id.clone(), cid, data);
+        self.hub.deliver(&peer_id, ev);
+    }
+
+    fn send(&self, cid: Cid, data: Vec<u8>) {
+        for peer_id in self.hub.peers(&self.peer_id) {
+            self.send_to(peer_id, cid, data.clone());
+        }
+    }
+
+    fn subscribe(&self) -> Self::Subscription {
+        let (tx, rx) = unbounded();
+        if let Some(subscribers) = self
+            .hub
+            .state
+            .lock()
+            .unwrap()
+            .subscribers
+            .get_mut(&self.peer_id)
+        {
+            subscribers.push(tx);
+        }
+        MockSubscription { events: rx }
+    }
+}
+
+pub struct MockSubscription {
+    events: UnboundedReceiver<NetworkEvent>,
+}
+
+impl Stream for MockSubscription {
+    type Item = NetworkEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.events).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Session, SessionConfig, SessionTimeout};
+    use ipfs_embed::db::StorageService;
+    use ipfs_embed::Ipfs;
+    use libipld::block::Block;
+    use libipld::cbor::DagCborCodec;
+    use libipld::ipld::Ipld;
+    use libipld::multihash::SHA2_256;
+    use libipld::raw::RawCodec;
+    use libipld::store::{DefaultStoreParams, Store};
+
+    type DefaultIpfs = Ipfs<
+        DefaultStoreParams,
+        StorageService<DefaultStoreParams>,
+        MockNetwork<DefaultStoreParams>,
+    >;
+
+    fn create_store(hub: &MockHub) -> DefaultIpfs {
+        let sled_config = sled::Config::new().temporary(true);
+        let storage = StorageService::open(&sled_config, 10, Duration::from_secs(10)).unwrap();
+        Ipfs::new(
+            Arc::new(storage),
+            Arc::new(hub.network()),
+            Duration::from_secs(1),
+        )
+    }
+
+    #[async_std::test]
+    async fn test_exchange() {
+        let hub = MockHub::new(MockConfig {
+            latency: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let store1 = create_store(&hub);
+        let store2 = create_store(&hub);
+        let block = Block::encode(RawCodec, SHA2_256, b"test_exchange").unwrap();
+        store1.insert(&block).await.unwrap();
+        let block2 = store2.get(block.cid()).await.unwrap();
+        assert_eq!(block.data(), block2.data());
+    }
+
+    #[async_std::test]
+    async fn test_session() {
+        let hub = MockHub::new(Default::default());
+        let store = create_store(&hub);
+        let leaf = Block::encode(RawCodec, SHA2_256, b"test_session").unwrap();
+        let ipld = Ipld::List(vec![Ipld::Link(*leaf.cid())]);
+        let root = Block::encode(DagCborCodec, SHA2_256, &ipld).unwrap();
+        store.insert(&leaf).await.unwrap();
+        store.insert(&root).await.unwrap();
+
+        let net = hub.network::<DefaultStoreParams>();
+        let mut session = Session::new(&net, SessionConfig::default());
+        let blocks = session.fetch(*root.cid()).await.unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(session.peers()[0].1.blocks, 2);
+    }
+
+    #[async_std::test]
+    async fn test_session_timeout() {
+        let hub = MockHub::new(MockConfig {
+            loss: 1.0,
+            ..Default::default()
+        });
+        let store = create_store(&hub);
+        let block = Block::encode(RawCodec, SHA2_256, b"test_session_timeout").unwrap();
+        store.insert(&block).await.unwrap();
+
+        let net = hub.network::<DefaultStoreParams>();
+        let config = SessionConfig {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut session = Session::new(&net, config);
+        let err = session.fetch(*block.cid()).await.unwrap_err();
+        assert!(err.downcast_ref::<SessionTimeout>().is_some());
+    }
+}
@@ -0,0 +1,194 @@
+use ipfs_embed_core::{Multiaddr, NetworkEvent, PeerId};
+use std::collections::{HashMap, HashSet};
+
+/// Information about a peer of the offchain network.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeerInfo {
+    pub peer_id: PeerId,
+    /// Known addresses of the peer.
+    pub addresses: Vec<Multiaddr>,
+    /// Whether the peer is currently connected.
+    pub connected: bool,
+    /// Whether the peer is banned.
+    pub banned: bool,
+    /// Number of blocks the peer served.
+    pub served_blocks: u64,
+}
+
+/// Tracks the peers of the offchain network.
+#[derive(Default)]
+pub(crate) struct Peers {
+    peers: HashMap<PeerId, PeerInfo>,
+    banned: HashSet<PeerId>,
+    reserved: HashSet<PeerId>,
+}
+
+impl Peers {
+    fn entry(&mut self, peer_id: &PeerId) -> &mut PeerInfo {
+        let banned = self.banned.contains(peer_id);
+        self.peers
+            .entry(peer_id.clone())
+            .or_insert_with(|| PeerInfo {
+                peer_id: peer_id.clone(),
+                addresses: Default::default(),
+                connected: false,
+                banned,
+                served_blocks: 0,
+            })
+    }
+
+    pub fn add_address(&mut self, peer_id: &PeerId, addr: Multiaddr) {
+        let info = self.entry(peer_id);
+        if !info.addresses.contains(&addr) {
+            info.addresses.push(addr);
+        }
+    }
+
+    pub fn addresses(&self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.peers
+            .get(peer_id)
+            .map(|info| info.addresses.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn connected(&mut self, peer_id: &PeerId) {
+        self.entry(peer_id).connected = true;
+    }
+
+    pub fn disconnected(&mut self, peer_id: &PeerId) {
+        self.entry(peer_id).connected = false;
+    }
+
+    pub fn served_block(&mut self, peer_id: &PeerId) {
+        self.entry(peer_id).served_blocks += 1;
+    }
+
+    pub fn ban(&mut self, peer_id: &PeerId) {
+        self.banned.insert(peer_id.clone());
+        self.entry(peer_id).banned = true;
+    }
+
+    pub fn unban(&mut self, peer_id: &PeerId) {
+        self.banned.remove(peer_id);
+        self.entry(peer_id).banned = false;
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.banned.contains(peer_id)
+    }
+
+    /// Marks a peer as dialed through the reserved peers.
+    ///
+    /// Returns false if it already is.
+    pub fn reserve(&mut self, peer_id: &PeerId) -> bool {
+        self.reserved.insert(peer_id.clone())
+    }
+
+    /// Returns false if the peer wasn't dialed through the reserved peers.
+    pub fn unreserve(&mut self, peer_id: &PeerId) -> bool {
+        self.reserved.remove(peer_id)
+    }
+
+    pub fn take_reserved(&mut self) -> Vec<PeerId> {
+        self.reserved.drain().collect()
+    }
+
+    /// Removes banned peers and orders the remaining peers by the number of
+    /// blocks they served.
+    pub fn rank(&self, peers: impl IntoIterator<Item = PeerId>) -> Vec<PeerId> {
+        let mut peers: Vec<_> = peers
+            .into_iter()
+            .filter(|peer_id| !self.is_banned(peer_id))
+            .collect();
+        peers.sort_by_key(|peer_id| {
+            std::cmp::Reverse(
+                self.peers
+                    .get(peer_id)
+                    .map(|info| info.served_blocks)
+                    .unwrap_or_default(),
+            )
+        });
+        peers
+    }
+
+    /// Drops events and providers from banned peers and records served
+    /// blocks.
+    pub fn filter(&mut self, ev: NetworkEvent) -> Option<NetworkEvent> {
+        match ev {
+            NetworkEvent::Providers(cid, providers) => Some(NetworkEvent::Providers(
+                cid,
+                providers
+                    .into_iter()
+                    .filter(|peer_id| !self.is_banned(peer_id))
+                    .collect(),
+            )),
+            NetworkEvent::ReceivedBlock(peer_id, cid, data) => {
+                if self.is_banned(&peer_id) {
+                    return None;
+                }
+                self.served_block(&peer_id);
+                Some(NetworkEvent::ReceivedBlock(peer_id, cid, data))
+            }
+            NetworkEvent::ReceivedWant(peer_id, cid, priority) => {
+                if self.is_banned(&peer_id) {
+                    return None;
+                }
+                Some(NetworkEvent::ReceivedWant(peer_id, cid, priority))
+            }
+            ev => Some(ev),
+        }
+    }
+
+    /// Returns all known peers, connected peers first and ordered by the
+    /// number of blocks they served.
+    pub fn list(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<_> = self.peers.values().cloned().collect();
+        peers.sort_by_key(|info| {
+            (
+                std::cmp::Reverse(info.connected),
+                std::cmp::Reverse(info.served_blocks),
+            )
+        });
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let mut peers = Peers::default();
+        let a = PeerId::random();
+        let b = PeerId::random();
+        let c = PeerId::random();
+        peers.served_block(&b);
+        peers.connected(&c);
+        peers.ban(&a);
+
+        let providers = peers.rank(vec![a.clone(), c.clone(), b.clone()]);
+        assert_eq!(providers, vec![b.clone(), c.clone()]);
+
+        let list = peers.list();
+        assert_eq!(list[0].peer_id, c);
+        assert_eq!(list[1].peer_id, b);
+        assert!(list[2].banned);
+
+        peers.unban(&a);
+        assert!(!peers.is_banned(&a));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut peers = Peers::default();
+        let a = PeerId::random();
+        assert!(peers.reserve(&a));
+        assert!(!peers.reserve(&a));
+        assert!(peers.unreserve(&a));
+        assert!(!peers.unreserve(&a));
+        peers.reserve(&a);
+        assert_eq!(peers.take_reserved(), vec![a]);
+        assert!(peers.take_reserved().is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+use anyhow::Result;
+use async_std::future::timeout;
+use futures::stream::StreamExt;
+use ipfs_embed_core::{Cid, Network, NetworkEvent, PeerId, StoreParams};
+use libipld::block::Block;
+use libipld::codec::Decode;
+use libipld::ipld::Ipld;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("session timed out waiting for {} blocks", .0.len())]
+pub struct SessionTimeout(pub Vec<Cid>);
+
+#[derive(Debug, Error)]
+#[error("network subscription closed")]
+pub struct SessionClosed;
+
+/// Configuration of a bitswap session.
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    /// Maximum number of outstanding wants.
+    pub batch_size: usize,
+    /// Time to wait for a block before looking for providers and retrying.
+    ///
+    /// A block that isn't received after retrying fails the session.
+    pub timeout: Duration,
+    /// Priority of the wants.
+    pub priority: i32,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 32,
+            timeout: Duration::from_secs(5),
+            priority: 1000,
+        }
+    }
+}
+
+/// Blocks served by a peer during a session.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PeerScore {
+    /// Number of blocks the peer served.
+    pub blocks: u64,
+    /// Total time between wanting and receiving the blocks.
+    pub latency: Duration,
+}
+
+impl PeerScore {
+    /// Average time the peer took to serve a block.
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.blocks == 0 {
+            None
+        } else {
+            Some(self.latency / self.blocks as u32)
+        }
+    }
+}
+
+/// Bitswap session fetching a dag from the network.
+///
+/// Wants are issued in batches and the peers are scored by the latency of
+/// the blocks they serve. When a want times out the providers of the block
+/// are looked up and dialed fastest first. Outstanding wants are cancelled
+/// when the session is dropped.
+pub struct Session<'a, S: StoreParams, N: Network<S>> {
+    _marker: PhantomData<S>,
+    net: &'a N,
+    events: N::Subscription,
+    config: SessionConfig,
+    wants: HashMap<Cid, Instant>,
+    retries: HashSet<Cid>,
+    peers: HashMap<PeerId, PeerScore>,
+}
+
+impl<'a, S, N> Session<'a, S, N>
+where
+    S: StoreParams,
+    N: Network<S>,
+    Ipld: Decode<S::Codecs>,
+{
+    pub fn new(net: &'a N, config: SessionConfig) -> Self {
+        Self {
+            _marker: PhantomData,
+            events: net.subscribe(),
+            net,
+            config,
+            wants: Default::default(),
+            retries: Default::default(),
+            peers: Default::default(),
+        }
+    }
+
+    /// Returns the peers that served blocks, fastest first.
+    pub fn peers(&self) -> Vec<(PeerId, PeerScore)> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter()
+            .map(|(peer_id, score)| (peer_id.clone(), score.clone()))
+            .collect();
+        peers.sort_by_key(|(_, score)| score.average_latency());
+        peers
+    }
+
+    /// Fetches `root` and all blocks it references.
+    pub async fn fetch(&mut self, root: Cid) -> Result<Vec<Block<S>>> {
+        let mut queue = VecDeque::new();
+        let mut seen = HashSet::new();
+        let mut blocks = Vec::new();
+        queue.push_back(root);
+        seen.insert(root);
+
+        while !queue.is_empty() || !self.wants.is_empty() {
+            while self.wants.len() < self.config.batch_size {
+                if let Some(cid) = queue.pop_front() {
+                    self.want(cid);
+                } else {
+                    break;
+                }
+            }
+
+            let wait = self
+                .wants
+                .values()
+                .map(|started| self.config.timeout.saturating_sub(started.elapsed()))
+                .min()
+                .unwrap_or_default();
+            match timeout(wait, self.events.next()).await {
+                Ok(Some(NetworkEvent::ReceivedBlock(peer_id, cid, data))) => {
+                    let started = if let Some(started) = self.wants.remove(&cid) {
+                        started
+                    } else {
+                        continue;
+                    };
+                    let score = self.peers.entry(peer_id).or_default();
+                    score.blocks += 1;
+                    score.latency += started.elapsed();
+
+                    let block = Block::<S>::new(cid, data)?;
+                    for cid in references(&block)? {
+                        if seen.insert(cid) {
+                            queue.push_back(cid);
+                        }
+                    }
+                    blocks.push(block);
+                }
+                Ok(Some(NetworkEvent::Providers(_, providers))) => {
+                    let mut providers: Vec<_> = providers.into_iter().collect();
+                    providers.sort_by_key(|peer_id| {
+                        self.peers
+                            .get(peer_id)
+                            .and_then(|score| score.average_latency())
+                            .unwrap_or(self.config.timeout)
+                    });
+                    for peer_id in providers {
+                        self.net.connect(peer_id);
+                    }
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => return Err(SessionClosed.into()),
+                Err(_) => {}
+            }
+
+            let expired: Vec<_> = self
+                .wants
+                .iter()
+                .filter(|(_, started)| started.elapsed() >= self.config.timeout)
+                .map(|(cid, _)| *cid)
+                .collect();
+            for cid in expired {
+                if !self.retries.insert(cid) {
+                    return Err(SessionTimeout(self.wants.keys().copied().collect()).into());
+                }
+                self.net.providers(&cid);
+                self.want(cid);
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn want(&mut self, cid: Cid) {
+        self.net.want(cid, self.config.priority);
+        self.wants.insert(cid, Instant::now());
+    }
+}
+
+impl<'a, S: StoreParams, N: Network<S>> Drop for Session<'a, S, N> {
+    fn drop(&mut self) {
+        for (cid, _) in self.wants.drain() {
+            self.net.cancel(cid);
+        }
+    }
+}
+
+fn references<S: StoreParams>(block: &Block<S>) -> Result<HashSet<Cid>>
+where
+    Ipld: Decode<S::Codecs>,
+{
+    let codec = S::Codecs::try_from(block.cid().codec())?;
+    let ipld = Ipld::decode(codec, &mut block.data())?;
+    Ok(ipld.references())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+    use ipfs_embed_core::Multiaddr;
+    use libipld::multihash::SHA2_256;
+    use libipld::raw::RawCodec;
+    use libipld::store::DefaultStoreParams;
+    use std::sync::Mutex;
+
+    /// Network that ignores the first want and serves the block after that.
+    struct LossyNetwork {
+        peer_id: PeerId,
+        block: Block<DefaultStoreParams>,
+        wants: Mutex<usize>,
+        subscribers: Mutex<Vec<UnboundedSender<NetworkEvent>>>,
+    }
+
+    impl Network<DefaultStoreParams> for LossyNetwork {
+        type Subscription = UnboundedReceiver<NetworkEvent>;
+
+        fn local_peer_id(&self) -> &PeerId {
+            &self.peer_id
+        }
+
+        fn external_addresses(&self) -> Vec<Multiaddr> {
+            Default::default()
+        }
+
+        fn provide(&self, _cid: &Cid) {}
+
+        fn unprovide(&self, _cid: &Cid) {}
+
+        fn providers(&self, _cid: &Cid) {}
+
+        fn connect(&self, _peer_id: PeerId) {}
+
+        fn want(&self, cid: Cid, _priority: i32) {
+            let mut wants = self.wants.lock().unwrap();
+            *wants += 1;
+            if *wants < 2 {
+                return;
+            }
+            let data = self.block.data().to_vec();
+            let ev = NetworkEvent::ReceivedBlock(self.peer_id.clone(), cid, data);
+            for tx in self.subscribers.lock().unwrap().iter() {
+                tx.unbounded_send(ev.clone()).ok();
+            }
+        }
+
+        fn cancel(&self, _cid: Cid) {}
+
+        fn send_to(&self, _peer_id: PeerId, _cid: Cid, _data: Vec<u8>) {}
+
+        fn send(&self, _cid: Cid, _data: Vec<u8>) {}
+
+        fn subscribe(&self) -> Self::Subscription {
+            let (tx, rx) = unbounded();
+            self.subscribers.lock().unwrap().push(tx);
+            rx
+        }
+    }
+
+    #[async_std::test]
+    async fn test_session_retry() {
+        let block = Block::encode(RawCodec, SHA2_256, b"test_session_retry").unwrap();
+        let net = LossyNetwork {
+            peer_id: PeerId::random(),
+            block: block.clone(),
+            wants: Default::default(),
+            subscribers: Default::default(),
+        };
+        let config = SessionConfig {
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut session = Session::new(&net, config);
+        let blocks = session.fetch(*block.cid()).await.unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(*net.wants.lock().unwrap(), 2);
+    }
+}
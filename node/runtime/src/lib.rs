@@ -1,8 +1,10 @@
 //! Default node service implementation.
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::weights::{constants, Weight};
-use sp_runtime::traits::Block as BlockT;
+use frame_support::traits::KeyOwnerProofSystem;
+use frame_support::weights::{constants, IdentityFee, Weight};
+use sp_core::crypto::KeyTypeId;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
 use sp_runtime::Perbill;
 use sp_std::prelude::*;
 
@@ -12,6 +14,8 @@ include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 pub type Hasher = sp_runtime::traits::BlakeTwo256;
 pub type Hash = sp_core::H256;
 pub type BlockNumber = u32;
+pub type Balance = u128;
+pub type Moment = u64;
 pub type Header = sp_runtime::generic::Header<BlockNumber, Hasher>;
 pub type SignedExtra = (
     frame_system::CheckSpecVersion<Runtime>,
@@ -20,6 +24,7 @@ pub type SignedExtra = (
     frame_system::CheckEra<Runtime>,
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
+    pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
 );
 pub type UncheckedExtrinsic = sp_runtime::generic::UncheckedExtrinsic<
     sp_runtime::AccountId32,
@@ -39,6 +44,17 @@ pub type Executive = frame_executive::Executive<
 pub type AuraId = sp_consensus_aura::sr25519::AuthorityId;
 pub type GrandpaId = sp_finality_grandpa::AuthorityId;
 
+/// Target block time, aura produces one block per slot.
+pub const MILLISECS_PER_BLOCK: Moment = 1000;
+pub const SLOT_DURATION: Moment = MILLISECS_PER_BLOCK;
+
+sp_runtime::impl_opaque_keys! {
+    pub struct SessionKeys {
+        pub aura: Aura,
+        pub grandpa: Grandpa,
+    }
+}
+
 pub const VERSION: sp_version::RuntimeVersion = sp_version::RuntimeVersion {
     spec_name: sp_runtime::create_runtime_str!("sunshine-node-utils"),
     impl_name: sp_runtime::create_runtime_str!("sunshine-node-utils"),
@@ -64,6 +80,12 @@ frame_support::construct_runtime!(
         UncheckedExtrinsic = UncheckedExtrinsic
     {
         System: frame_system::{Module, Call, Storage, Config, Event<T>},
+        Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+        Aura: pallet_aura::{Module, Config<T>, Inherent(Timestamp)},
+        Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
+        Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+        TransactionPayment: pallet_transaction_payment::{Module, Storage},
+        Chain: sunshine_chain_pallet::{Module, Call, Storage, Event<T>},
     }
 );
 
@@ -73,6 +95,10 @@ frame_support::parameter_types! {
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
     pub const MaximumBlockLength: u32 = 5 * 1024 * 1024;
     pub const Version: sp_version::RuntimeVersion = VERSION;
+    pub const MinimumPeriod: Moment = SLOT_DURATION / 2;
+    pub const ExistentialDeposit: Balance = 1;
+    pub const MaxLocks: u32 = 50;
+    pub const TransactionByteFee: Balance = 1;
 }
 
 impl frame_system::Trait for Runtime {
@@ -90,7 +116,7 @@ impl frame_system::Trait for Runtime {
     type Header = Header;
     type AccountId = sp_runtime::AccountId32;
     type Index = u32;
-    type AccountData = ();
+    type AccountData = pallet_balances::AccountData<Balance>;
     type Lookup = sp_runtime::traits::IdentityLookup<Self::AccountId>;
 
     // Config
@@ -110,6 +136,55 @@ impl frame_system::Trait for Runtime {
     type OnKilledAccount = ();
 }
 
+impl pallet_timestamp::Trait for Runtime {
+    type Moment = Moment;
+    type OnTimestampSet = Aura;
+    type MinimumPeriod = MinimumPeriod;
+    type WeightInfo = ();
+}
+
+impl pallet_aura::Trait for Runtime {
+    type AuthorityId = AuraId;
+}
+
+impl pallet_grandpa::Trait for Runtime {
+    type Event = Event;
+    type Call = Call;
+    type KeyOwnerProofSystem = ();
+    type KeyOwnerProof =
+        <Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
+    type KeyOwnerIdentification = <Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(
+        KeyTypeId,
+        GrandpaId,
+    )>>::IdentificationTuple;
+    type HandleEquivocation = ();
+    type WeightInfo = ();
+}
+
+impl pallet_balances::Trait for Runtime {
+    type MaxLocks = MaxLocks;
+    type Balance = Balance;
+    type Event = Event;
+    type DustRemoval = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+impl pallet_transaction_payment::Trait for Runtime {
+    type Currency = Balances;
+    type OnTransactionPayment = ();
+    type TransactionByteFee = TransactionByteFee;
+    type WeightToFee = IdentityFee<Balance>;
+    type FeeMultiplierUpdate = ();
+}
+
+impl sunshine_chain_pallet::Trait for Runtime {
+    type ChainId = u64;
+    type Number = u64;
+    type Event = Event;
+}
+
 sp_api::impl_runtime_apis! {
     impl sp_api::Core<Block> for Runtime {
         fn version() -> sp_version::RuntimeVersion {
@@ -173,35 +248,35 @@ sp_api::impl_runtime_apis! {
 
     impl sp_consensus_aura::AuraApi<Block, AuraId> for Runtime {
         fn slot_duration() -> u64 {
-            1
+            Aura::slot_duration()
         }
 
         fn authorities() -> Vec<AuraId> {
-            Default::default()
+            Aura::authorities()
         }
     }
 
     impl sp_session::SessionKeys<Block> for Runtime {
-        fn generate_session_keys(_seed: Option<Vec<u8>>) -> Vec<u8> {
-            Default::default()
+        fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
+            SessionKeys::generate(seed)
         }
 
         fn decode_session_keys(
-            _encoded: Vec<u8>,
-        ) -> Option<Vec<(Vec<u8>, sp_core::crypto::KeyTypeId)>> {
-            Default::default()
+            encoded: Vec<u8>,
+        ) -> Option<Vec<(Vec<u8>, KeyTypeId)>> {
+            SessionKeys::decode_into_raw_public_keys(&encoded)
         }
     }
 
     impl sp_finality_grandpa::GrandpaApi<Block> for Runtime {
         fn grandpa_authorities() -> sp_finality_grandpa::AuthorityList {
-            Default::default()
+            Grandpa::grandpa_authorities()
         }
 
         fn submit_report_equivocation_unsigned_extrinsic(
             _equivocation_proof: sp_finality_grandpa::EquivocationProof<
                 <Block as BlockT>::Hash,
-                sp_api::NumberFor<Block>,
+                NumberFor<Block>,
             >,
             _key_owner_proof: sp_finality_grandpa::OpaqueKeyOwnershipProof,
         ) -> Option<()> {
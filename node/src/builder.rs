@@ -0,0 +1,692 @@
+//! Generic node service builder.
+use crate::consensus::{AuraGrandpa, AuraGrandpaLight};
+use crate::rpc::{OffchainStoreHandle, RpcDeps, RpcExtension};
+use sc_client_api::{RemoteBackend, StateBackendFor};
+use sc_executor::NativeExecutionDispatch;
+use sc_keystore::KeyStorePtr;
+use sc_network::config::{FinalityProofProvider, OnDemand};
+use sc_network::NetworkService;
+use sc_service::error::Error;
+use sc_service::{
+    Configuration, PartialComponents, Role, RpcHandlers, TaskManager, TelemetryConnectionSinks,
+};
+use sp_api::ConstructRuntimeApi;
+use sp_consensus::block_validation::BlockAnnounceValidator;
+use sp_consensus::import_queue::BoxFinalityProofRequestBuilder;
+use sp_inherents::InherentDataProviders;
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, HashFor};
+use std::path::Path;
+use std::sync::Arc;
+use substrate_prometheus_endpoint::Registry;
+use tiny_multihash::MultihashDigest;
+
+pub type FullClient<B, RA, E> = sc_service::TFullClient<B, RA, E>;
+pub type FullBackend<B> = sc_service::TFullBackend<B>;
+pub type FullSelectChain<B> = sc_consensus::LongestChain<FullBackend<B>, B>;
+pub type FullPool<B, RA, E> = sc_transaction_pool::FullPool<B, FullClient<B, RA, E>>;
+pub type FullApi<B, RA, E> = <RA as ConstructRuntimeApi<B, FullClient<B, RA, E>>>::RuntimeApi;
+pub type LightClient<B, RA, E> = sc_service::TLightClient<B, RA, E>;
+pub type LightBackend<B> = sc_service::TLightBackend<B>;
+pub type LightPool<B, RA, E> =
+    sc_transaction_pool::LightPool<B, LightClient<B, RA, E>, OnDemand<B>>;
+pub type LightApi<B, RA, E> = <RA as ConstructRuntimeApi<B, LightClient<B, RA, E>>>::RuntimeApi;
+pub type ImportQueue<B, C> = sp_consensus::DefaultImportQueue<B, C>;
+pub type Network<B, M> = Arc<NetworkService<B, <B as BlockT>::Hash, M>>;
+pub type FullPartial<B, RA, E> = PartialComponents<
+    FullClient<B, RA, E>,
+    FullBackend<B>,
+    FullSelectChain<B>,
+    ImportQueue<B, FullClient<B, RA, E>>,
+    FullPool<B, RA, E>,
+    (),
+>;
+
+type RpcFactory<C, P> = Box<dyn Fn(RpcDeps<C, P>) -> RpcExtension + Send>;
+type BlockAnnounceValidatorFactory<C, B> =
+    Box<dyn FnOnce(Arc<C>) -> Box<dyn BlockAnnounceValidator<B> + Send> + Send>;
+
+/// Runtime apis required by the node service, independent of the consensus.
+pub trait RuntimeApiCollection<B: BlockT>:
+    sp_api::ApiExt<B, Error = sp_blockchain::Error>
+    + sp_api::Metadata<B>
+    + sp_block_builder::BlockBuilder<B>
+    + sp_transaction_pool::runtime_api::TaggedTransactionQueue<B>
+    + sp_offchain::OffchainWorkerApi<B>
+    + sp_session::SessionKeys<B>
+where
+    <Self as sp_api::ApiExt<B>>::StateBackend: sp_api::StateBackend<HashFor<B>>,
+{
+}
+
+impl<B, Api> RuntimeApiCollection<B> for Api
+where
+    B: BlockT,
+    Api: sp_api::ApiExt<B, Error = sp_blockchain::Error>
+        + sp_api::Metadata<B>
+        + sp_block_builder::BlockBuilder<B>
+        + sp_transaction_pool::runtime_api::TaggedTransactionQueue<B>
+        + sp_offchain::OffchainWorkerApi<B>
+        + sp_session::SessionKeys<B>,
+    <Self as sp_api::ApiExt<B>>::StateBackend: sp_api::StateBackend<HashFor<B>>,
+{
+}
+
+/// Components of a full node available to the consensus engine before the
+/// network is started.
+pub struct FullParts<B: BlockT, RA, E> {
+    pub client: Arc<FullClient<B, RA, E>>,
+    pub backend: Arc<FullBackend<B>>,
+    pub keystore: KeyStorePtr,
+    pub select_chain: FullSelectChain<B>,
+    pub transaction_pool: Arc<FullPool<B, RA, E>>,
+    pub inherent_data_providers: InherentDataProviders,
+    pub task_manager: TaskManager,
+}
+
+/// Components of a light client available to the consensus engine before
+/// the network is started.
+pub struct LightParts<B: BlockT, RA, E> {
+    pub client: Arc<LightClient<B, RA, E>>,
+    pub backend: Arc<LightBackend<B>>,
+    pub on_demand: Arc<OnDemand<B>>,
+    pub task_manager: TaskManager,
+}
+
+/// Import queue and finality proof handling of a consensus engine.
+pub struct ImportSetup<B: BlockT, C> {
+    pub import_queue: ImportQueue<B, C>,
+    pub finality_proof_request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
+    pub finality_proof_provider: Option<Arc<dyn FinalityProofProvider<B>>>,
+}
+
+/// Components of a running full node passed to the consensus engine.
+pub struct FullService<B: BlockT, RA, E, M: MultihashDigest> {
+    pub client: Arc<FullClient<B, RA, E>>,
+    pub backend: Arc<FullBackend<B>>,
+    pub keystore: KeyStorePtr,
+    pub select_chain: FullSelectChain<B>,
+    pub transaction_pool: Arc<FullPool<B, RA, E>>,
+    pub inherent_data_providers: InherentDataProviders,
+    pub network: Network<B, M>,
+    pub telemetry_connection_sinks: TelemetryConnectionSinks,
+    pub prometheus_registry: Option<Registry>,
+    pub role: Role,
+    pub force_authoring: bool,
+    pub disable_grandpa: bool,
+    pub name: String,
+}
+
+/// Consensus engine of a full node.
+pub trait FullConsensus<B: BlockT, RA, E> {
+    /// Creates the import queue.
+    fn import_queue(
+        &mut self,
+        config: &Configuration,
+        parts: &FullParts<B, RA, E>,
+    ) -> Result<ImportSetup<B, FullClient<B, RA, E>>, Error>;
+
+    /// Rpc methods of the consensus engine, added to the rpc extensions.
+    fn rpc_extensions(&self) -> Option<Box<dyn Fn() -> RpcExtension + Send>> {
+        None
+    }
+
+    /// Starts block authoring and finality after the network was built.
+    fn start<M: MultihashDigest>(
+        self,
+        service: FullService<B, RA, E, M>,
+        task_manager: &mut TaskManager,
+    ) -> Result<(), Error>;
+}
+
+/// Consensus engine of a light client.
+pub trait LightConsensus<B: BlockT, RA, E> {
+    /// Creates the import queue.
+    fn import_queue(
+        &mut self,
+        config: &Configuration,
+        parts: &LightParts<B, RA, E>,
+    ) -> Result<ImportSetup<B, LightClient<B, RA, E>>, Error>;
+}
+
+/// Builds the services of full nodes and light clients.
+///
+/// The consensus engine is passed when building the service and defaults to
+/// aura with grandpa finality. Rpc extensions, the block announce validator
+/// and offchain workers can be customized before building.
+pub struct NodeBuilder<B: BlockT, RA, E> {
+    config: Configuration,
+    offchain: OffchainStoreHandle,
+    full_rpc: Option<RpcFactory<FullClient<B, RA, E>, FullPool<B, RA, E>>>,
+    light_rpc: Option<RpcFactory<LightClient<B, RA, E>, LightPool<B, RA, E>>>,
+    block_announce_validator: Option<BlockAnnounceValidatorFactory<FullClient<B, RA, E>, B>>,
+    offchain_workers: Option<bool>,
+}
+
+impl<B, RA, E> NodeBuilder<B, RA, E>
+where
+    B: BlockT,
+    E: NativeExecutionDispatch + 'static,
+    RA: Send + Sync + 'static,
+{
+    pub fn new(config: Configuration) -> Self {
+        Self {
+            config,
+            offchain: Default::default(),
+            full_rpc: None,
+            light_rpc: None,
+            block_announce_validator: None,
+            offchain_workers: None,
+        }
+    }
+
+    /// Offchain store served by the offchain rpc methods.
+    pub fn offchain_store(mut self, offchain: OffchainStoreHandle) -> Self {
+        self.offchain = offchain;
+        self
+    }
+
+    /// Replaces the rpc extensions of a full node.
+    pub fn full_rpc<F>(mut self, rpc: F) -> Self
+    where
+        F: Fn(RpcDeps<FullClient<B, RA, E>, FullPool<B, RA, E>>) -> RpcExtension + Send + 'static,
+    {
+        self.full_rpc = Some(Box::new(rpc));
+        self
+    }
+
+    /// Replaces the rpc extensions of a light client.
+    pub fn light_rpc<F>(mut self, rpc: F) -> Self
+    where
+        F: Fn(RpcDeps<LightClient<B, RA, E>, LightPool<B, RA, E>>) -> RpcExtension + Send + 'static,
+    {
+        self.light_rpc = Some(Box::new(rpc));
+        self
+    }
+
+    /// Validates block announcements of a full node.
+    ///
+    /// Light clients always use the default validator, building a light
+    /// client with a custom validator set fails.
+    pub fn block_announce_validator<F>(mut self, validator: F) -> Self
+    where
+        F: FnOnce(Arc<FullClient<B, RA, E>>) -> Box<dyn BlockAnnounceValidator<B> + Send>
+            + Send
+            + 'static,
+    {
+        self.block_announce_validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Overrides whether offchain workers are run, instead of using the
+    /// configuration.
+    pub fn offchain_workers(mut self, enabled: bool) -> Self {
+        self.offchain_workers = Some(enabled);
+        self
+    }
+
+    /// Creates the components of a full node without starting the network.
+    pub fn new_partial<C>(&self, consensus: &mut C) -> Result<FullPartial<B, RA, E>, Error>
+    where
+        C: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        new_partial(&self.config, consensus)
+    }
+
+    /// Builds a full node with aura and grandpa.
+    pub fn full<M>(self) -> Result<(TaskManager, RpcHandlers, Network<B, M>), Error>
+    where
+        M: MultihashDigest,
+        AuraGrandpa<B, RA, E>: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        self.build_full(AuraGrandpa::default())
+    }
+
+    /// Builds a full node with a custom consensus engine.
+    pub fn build_full<M, C>(
+        self,
+        mut consensus: C,
+    ) -> Result<(TaskManager, RpcHandlers, Network<B, M>), Error>
+    where
+        M: MultihashDigest,
+        C: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        let parts = full_parts(&self.config)?;
+        let setup = consensus.import_queue(&self.config, &parts)?;
+        let FullParts {
+            client,
+            backend,
+            keystore,
+            select_chain,
+            transaction_pool,
+            inherent_data_providers,
+            mut task_manager,
+        } = parts;
+        let NodeBuilder {
+            config,
+            offchain,
+            full_rpc,
+            block_announce_validator,
+            offchain_workers,
+            ..
+        } = self;
+
+        let (network, network_status_sinks, system_rpc_tx, network_starter) =
+            sc_service::BuildNetworkParams {
+                config: &config,
+                client: client.clone(),
+                transaction_pool: transaction_pool.clone(),
+                spawn_handle: task_manager.spawn_handle(),
+                import_queue: setup.import_queue,
+                on_demand: None,
+                block_announce_validator_builder: block_announce_validator,
+                finality_proof_request_builder: setup.finality_proof_request_builder,
+                finality_proof_provider: setup.finality_proof_provider,
+            }
+            .build_network::<M>()?;
+
+        if offchain_workers.unwrap_or(config.offchain_worker.enabled) {
+            sc_service::build_offchain_workers(
+                &config,
+                backend.clone(),
+                task_manager.spawn_handle(),
+                client.clone(),
+                network.clone(),
+            );
+        }
+
+        let role = config.role.clone();
+        let force_authoring = config.force_authoring;
+        let name = config.network.node_name.clone();
+        let disable_grandpa = config.disable_grandpa;
+        let prometheus_registry = config.prometheus_registry().cloned();
+        let telemetry_connection_sinks = TelemetryConnectionSinks::default();
+
+        let rpc = full_rpc.unwrap_or_else(|| {
+            Box::new(|deps| crate::rpc::full_extensions::<B, FullBackend<B>, _, _>(deps))
+        });
+        let consensus_rpc = consensus.rpc_extensions();
+        let rpc_extensions_builder = {
+            let client = client.clone();
+            let pool = transaction_pool.clone();
+            Box::new(move |deny_unsafe, _| {
+                let mut io = rpc(RpcDeps {
+                    client: client.clone(),
+                    pool: pool.clone(),
+                    deny_unsafe,
+                    offchain: offchain.clone(),
+                });
+                if let Some(consensus_rpc) = &consensus_rpc {
+                    io.extend_with(consensus_rpc());
+                }
+                io
+            })
+        };
+
+        let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+            network: network.clone(),
+            client: client.clone(),
+            keystore: keystore.clone(),
+            task_manager: &mut task_manager,
+            transaction_pool: transaction_pool.clone(),
+            telemetry_connection_sinks: telemetry_connection_sinks.clone(),
+            rpc_extensions_builder,
+            on_demand: None,
+            remote_blockchain: None,
+            backend: backend.clone(),
+            network_status_sinks,
+            system_rpc_tx,
+            config,
+        })?;
+
+        consensus.start(
+            FullService {
+                client,
+                backend,
+                keystore,
+                select_chain,
+                transaction_pool,
+                inherent_data_providers,
+                network: network.clone(),
+                telemetry_connection_sinks,
+                prometheus_registry,
+                role,
+                force_authoring,
+                disable_grandpa,
+                name,
+            },
+            &mut task_manager,
+        )?;
+
+        network_starter.start_network();
+        Ok((task_manager, rpc_handlers, network))
+    }
+
+    /// Builds a light client with aura and grandpa.
+    pub fn light<M>(self) -> Result<(TaskManager, RpcHandlers, Network<B, M>), Error>
+    where
+        M: MultihashDigest,
+        AuraGrandpaLight<B, RA, E>: LightConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, LightClient<B, RA, E>>,
+        LightApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<LightBackend<B>, B>>,
+    {
+        self.build_light(AuraGrandpaLight::default())
+    }
+
+    /// Builds a light client with a custom consensus engine.
+    pub fn build_light<M, C>(
+        self,
+        mut consensus: C,
+    ) -> Result<(TaskManager, RpcHandlers, Network<B, M>), Error>
+    where
+        M: MultihashDigest,
+        C: LightConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, LightClient<B, RA, E>>,
+        LightApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<LightBackend<B>, B>>,
+    {
+        let NodeBuilder {
+            config,
+            offchain,
+            light_rpc,
+            block_announce_validator,
+            offchain_workers,
+            ..
+        } = self;
+        if block_announce_validator.is_some() {
+            return Err(Error::Other(
+                "block announce validators are only supported by full nodes".into(),
+            ));
+        }
+        let (client, backend, keystore, task_manager, on_demand) =
+            sc_service::new_light_parts::<B, RA, E>(&config)?;
+
+        let transaction_pool = Arc::new(sc_transaction_pool::BasicPool::new_light(
+            config.transaction_pool.clone(),
+            config.prometheus_registry(),
+            task_manager.spawn_handle(),
+            client.clone(),
+            on_demand.clone(),
+        ));
+
+        let parts = LightParts {
+            client,
+            backend,
+            on_demand,
+            task_manager,
+        };
+        let setup = consensus.import_queue(&config, &parts)?;
+        let LightParts {
+            client,
+            backend,
+            on_demand,
+            mut task_manager,
+        } = parts;
+
+        let (network, network_status_sinks, system_rpc_tx, network_starter) =
+            sc_service::BuildNetworkParams {
+                config: &config,
+                client: client.clone(),
+                transaction_pool: transaction_pool.clone(),
+                spawn_handle: task_manager.spawn_handle(),
+                import_queue: setup.import_queue,
+                on_demand: Some(on_demand.clone()),
+                block_announce_validator_builder: None,
+                finality_proof_request_builder: setup.finality_proof_request_builder,
+                finality_proof_provider: setup.finality_proof_provider,
+            }
+            .build_network::<M>()?;
+
+        if offchain_workers.unwrap_or(config.offchain_worker.enabled) {
+            sc_service::build_offchain_workers(
+                &config,
+                backend.clone(),
+                task_manager.spawn_handle(),
+                client.clone(),
+                network.clone(),
+            );
+        }
+
+        let rpc = light_rpc.unwrap_or_else(|| Box::new(crate::rpc::light_extensions));
+        let rpc_extensions_builder = {
+            let client = client.clone();
+            let pool = transaction_pool.clone();
+            Box::new(move |deny_unsafe, _| {
+                rpc(RpcDeps {
+                    client: client.clone(),
+                    pool: pool.clone(),
+                    deny_unsafe,
+                    offchain: offchain.clone(),
+                })
+            })
+        };
+
+        let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+            remote_blockchain: Some(backend.remote_blockchain()),
+            transaction_pool,
+            task_manager: &mut task_manager,
+            on_demand: Some(on_demand),
+            rpc_extensions_builder,
+            telemetry_connection_sinks: TelemetryConnectionSinks::default(),
+            config,
+            client,
+            keystore,
+            backend,
+            network: network.clone(),
+            network_status_sinks,
+            system_rpc_tx,
+        })?;
+
+        network_starter.start_network();
+        Ok((task_manager, rpc_handlers, network))
+    }
+
+    /// Exports the blocks in the range from the database to a file.
+    pub fn export_blocks<C>(
+        self,
+        mut consensus: C,
+        output: &Path,
+        from: u32,
+        to: Option<u32>,
+        binary: bool,
+    ) -> Result<(), Error>
+    where
+        C: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        let PartialComponents {
+            client,
+            task_manager,
+            ..
+        } = self.new_partial(&mut consensus)?;
+        let output = std::fs::File::create(output)?;
+        let export = sc_service::chain_ops::export_blocks(
+            client,
+            output,
+            from.into(),
+            to.map(Into::into),
+            binary,
+        );
+        let res = futures::executor::block_on(export);
+        drop(task_manager);
+        res
+    }
+
+    /// Imports blocks from a file into the database.
+    pub fn import_blocks<C>(self, mut consensus: C, input: &Path, binary: bool) -> Result<(), Error>
+    where
+        C: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        let PartialComponents {
+            client,
+            task_manager,
+            import_queue,
+            ..
+        } = self.new_partial(&mut consensus)?;
+        let input = std::fs::File::open(input)?;
+        let import =
+            sc_service::chain_ops::import_blocks(client, import_queue, input, false, binary);
+        let res = futures::executor::block_on(import);
+        drop(task_manager);
+        res
+    }
+
+    /// Reimports a block.
+    pub fn check_block<C>(self, mut consensus: C, block: BlockId<B>) -> Result<(), Error>
+    where
+        C: FullConsensus<B, RA, E>,
+        RA: ConstructRuntimeApi<B, FullClient<B, RA, E>>,
+        FullApi<B, RA, E>:
+            RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+    {
+        let PartialComponents {
+            client,
+            task_manager,
+            import_queue,
+            ..
+        } = self.new_partial(&mut consensus)?;
+        let check = sc_service::chain_ops::check_block(client, import_queue, block);
+        let res = futures::executor::block_on(check);
+        drop(task_manager);
+        res
+    }
+}
+
+fn full_parts<B, RA, E>(config: &Configuration) -> Result<FullParts<B, RA, E>, Error>
+where
+    B: BlockT,
+    E: NativeExecutionDispatch + 'static,
+    RA: ConstructRuntimeApi<B, FullClient<B, RA, E>> + Send + Sync + 'static,
+    FullApi<B, RA, E>: RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+{
+    let (client, backend, keystore, task_manager) = sc_service::new_full_parts::<B, RA, E>(config)?;
+    let client = Arc::new(client);
+    let select_chain = sc_consensus::LongestChain::new(backend.clone());
+    let transaction_pool = sc_transaction_pool::BasicPool::new_full(
+        config.transaction_pool.clone(),
+        config.prometheus_registry(),
+        task_manager.spawn_handle(),
+        client.clone(),
+    );
+    Ok(FullParts {
+        client,
+        backend,
+        keystore,
+        select_chain,
+        transaction_pool,
+        inherent_data_providers: InherentDataProviders::new(),
+        task_manager,
+    })
+}
+
+/// Creates the components of a full node without starting the network.
+pub fn new_partial<B, RA, E, C>(
+    config: &Configuration,
+    consensus: &mut C,
+) -> Result<FullPartial<B, RA, E>, Error>
+where
+    B: BlockT,
+    E: NativeExecutionDispatch + 'static,
+    C: FullConsensus<B, RA, E>,
+    RA: ConstructRuntimeApi<B, FullClient<B, RA, E>> + Send + Sync + 'static,
+    FullApi<B, RA, E>: RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>,
+{
+    let parts = full_parts(config)?;
+    let setup = consensus.import_queue(config, &parts)?;
+    Ok(PartialComponents {
+        client: parts.client,
+        backend: parts.backend,
+        task_manager: parts.task_manager,
+        import_queue: setup.import_queue,
+        keystore: parts.keystore,
+        select_chain: parts.select_chain,
+        transaction_pool: parts.transaction_pool,
+        inherent_data_providers: parts.inherent_data_providers,
+        other: (),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{dev_chain_spec, empty_chain_spec, runtime, ChainSpec, Executor};
+    use crate::rpc::RpcExtension;
+    use sp_keyring::AccountKeyring;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use substrate_subxt::client::{DatabaseConfig, KeystoreConfig, Role, SubxtClientConfig};
+    use sunshine_codec::Multihash;
+    use tempdir::TempDir;
+
+    type Builder = NodeBuilder<runtime::OpaqueBlock, runtime::RuntimeApi, Executor>;
+
+    fn config(tmp: &TempDir, role: Role, chain_spec: ChainSpec) -> Configuration {
+        SubxtClientConfig {
+            impl_name: "impl_name",
+            impl_version: "impl_version",
+            author: "author",
+            copyright_start_year: 2020,
+            db: DatabaseConfig::ParityDb {
+                path: tmp.path().join("db"),
+            },
+            keystore: KeystoreConfig::InMemory,
+            role,
+            chain_spec,
+            telemetry: None,
+        }
+        .into_service_config()
+    }
+
+    #[test]
+    fn test_build_full() {
+        let tmp = TempDir::new("full").unwrap();
+        let config = config(
+            &tmp,
+            Role::Authority(AccountKeyring::Alice),
+            dev_chain_spec(),
+        );
+        let called = Arc::new(AtomicBool::new(false));
+        let called2 = called.clone();
+        let (_task_manager, _rpc, _network) = Builder::new(config)
+            .full_rpc(move |_| {
+                called2.store(true, Ordering::SeqCst);
+                RpcExtension::default()
+            })
+            .full::<Multihash>()
+            .unwrap();
+        assert!(called.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_build_light() {
+        let tmp = TempDir::new("light").unwrap();
+        let config = config(&tmp, Role::Light, empty_chain_spec());
+        let (_task_manager, _rpc, _network) = Builder::new(config).light::<Multihash>().unwrap();
+    }
+
+    #[test]
+    fn test_light_rejects_block_announce_validator() {
+        let tmp = TempDir::new("light").unwrap();
+        let config = config(&tmp, Role::Light, empty_chain_spec());
+        let res = Builder::new(config)
+            .block_announce_validator(|_| {
+                Box::new(sp_consensus::block_validation::DefaultBlockAnnounceValidator)
+            })
+            .light::<Multihash>();
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Chain spec builder for development chains and local testnets.
+use sc_network::config::MultiaddrWithPeerId;
+use sc_service::{ChainType, GenericChainSpec, Properties, RuntimeGenesis};
+use sp_core::{ed25519, sr25519, Pair};
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::AccountId32;
+use thiserror::Error;
+
+pub type AuraId = sp_consensus_aura::sr25519::AuthorityId;
+pub type GrandpaId = sp_finality_grandpa::AuthorityId;
+
+/// Balance of endowed accounts if none is set.
+pub const DEFAULT_ENDOWMENT: u128 = 1 << 60;
+
+#[derive(Debug, Error)]
+#[error("only {1} authorities can be derived from the keyring, got {0}")]
+pub struct TooManyAuthorities(pub usize, pub usize);
+
+#[derive(Debug, Error)]
+#[error("invalid suri: {0:?}")]
+pub struct InvalidSuri(pub sp_core::crypto::SecretStringError);
+
+/// Keys of a genesis authority.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Authority {
+    pub account_id: AccountId32,
+    pub aura: AuraId,
+    pub grandpa: GrandpaId,
+}
+
+impl Authority {
+    /// Derives the keys of an authority from a secret uri like `//Alice`.
+    pub fn from_suri(suri: &str) -> Result<Self, InvalidSuri> {
+        let sr25519 = sr25519::Pair::from_string(suri, None)
+            .map_err(InvalidSuri)?
+            .public();
+        let ed25519 = ed25519::Pair::from_string(suri, None)
+            .map_err(InvalidSuri)?
+            .public();
+        Ok(Self {
+            account_id: sr25519.into(),
+            aura: sr25519.into(),
+            grandpa: ed25519.into(),
+        })
+    }
+
+    /// Derives the keys of an authority from a well known keyring seed.
+    pub fn from_keyring(keyring: Sr25519Keyring) -> Self {
+        Self::from_suri(&keyring.to_seed()).expect("keyring seeds are valid; qed")
+    }
+}
+
+/// Parameters passed to the runtime genesis constructor.
+#[derive(Clone, Debug)]
+pub struct Genesis {
+    /// Aura and grandpa authorities.
+    pub authorities: Vec<Authority>,
+    /// Accounts and their initial balance.
+    pub endowed_accounts: Vec<(AccountId32, u128)>,
+}
+
+impl Genesis {
+    pub fn aura_authorities(&self) -> Vec<AuraId> {
+        self.authorities.iter().map(|a| a.aura.clone()).collect()
+    }
+
+    /// Grandpa authorities with equal weight.
+    pub fn grandpa_authorities(&self) -> Vec<(GrandpaId, u64)> {
+        self.authorities
+            .iter()
+            .map(|a| (a.grandpa.clone(), 1))
+            .collect()
+    }
+}
+
+/// Builds chain specs with keyring authorities and prefunded accounts.
+///
+/// The runtime genesis is created by a closure receiving the [`Genesis`]
+/// parameters, so the builder works with any runtime.
+#[derive(Clone, Debug)]
+pub struct ChainSpecBuilder {
+    name: String,
+    id: String,
+    chain_type: ChainType,
+    authorities: Vec<Authority>,
+    endowed_accounts: Vec<AccountId32>,
+    endowment: u128,
+    boot_nodes: Vec<MultiaddrWithPeerId>,
+    protocol_id: Option<String>,
+    properties: Properties,
+}
+
+impl ChainSpecBuilder {
+    pub fn new(name: &str, id: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            id: id.to_string(),
+            chain_type: ChainType::Local,
+            authorities: Default::default(),
+            endowed_accounts: Default::default(),
+            endowment: DEFAULT_ENDOWMENT,
+            boot_nodes: Default::default(),
+            protocol_id: None,
+            properties: Default::default(),
+        }
+    }
+
+    /// Development chain with `Alice` as the only authority.
+    pub fn dev() -> Self {
+        Self::new("Development", "dev")
+            .chain_type(ChainType::Development)
+            .authority(Authority::from_keyring(Sr25519Keyring::Alice))
+            .endow_keyring()
+    }
+
+    /// Local testnet with `n` keyring authorities.
+    pub fn local_testnet(n: usize) -> Result<Self, TooManyAuthorities> {
+        Ok(Self::new("Local Testnet", "local_testnet")
+            .keyring_authorities(n)?
+            .endow_keyring())
+    }
+
+    pub fn chain_type(mut self, chain_type: ChainType) -> Self {
+        self.chain_type = chain_type;
+        self
+    }
+
+    pub fn authority(mut self, authority: Authority) -> Self {
+        self.authorities.push(authority);
+        self
+    }
+
+    /// Adds the first `n` keyring accounts (`Alice`, `Bob`, ...) as authorities.
+    pub fn keyring_authorities(mut self, n: usize) -> Result<Self, TooManyAuthorities> {
+        let keyring: Vec<_> = Sr25519Keyring::iter().collect();
+        if n > keyring.len() {
+            return Err(TooManyAuthorities(n, keyring.len()));
+        }
+        self.authorities
+            .extend(keyring.into_iter().take(n).map(Authority::from_keyring));
+        Ok(self)
+    }
+
+    pub fn endow(mut self, account_id: AccountId32) -> Self {
+        self.endowed_accounts.push(account_id);
+        self
+    }
+
+    /// Endows all keyring accounts.
+    pub fn endow_keyring(mut self) -> Self {
+        self.endowed_accounts
+            .extend(Sr25519Keyring::iter().map(|k| k.to_account_id()));
+        self
+    }
+
+    /// Initial balance of the endowed accounts.
+    pub fn endowment(mut self, endowment: u128) -> Self {
+        self.endowment = endowment;
+        self
+    }
+
+    pub fn boot_node(mut self, addr: MultiaddrWithPeerId) -> Self {
+        self.boot_nodes.push(addr);
+        self
+    }
+
+    pub fn protocol_id(mut self, protocol_id: &str) -> Self {
+        self.protocol_id = Some(protocol_id.to_string());
+        self
+    }
+
+    /// Sets the token symbol and decimals shown by wallets.
+    pub fn token(mut self, symbol: &str, decimals: u8) -> Self {
+        self.properties
+            .insert("tokenSymbol".into(), symbol.to_string().into());
+        self.properties
+            .insert("tokenDecimals".into(), decimals.into());
+        self
+    }
+
+    pub fn ss58_format(mut self, format: u8) -> Self {
+        self.properties.insert("ss58Format".into(), format.into());
+        self
+    }
+
+    /// Returns the genesis parameters.
+    pub fn genesis(&self) -> Genesis {
+        let mut endowed_accounts: Vec<(AccountId32, u128)> = Vec::new();
+        for account_id in &self.endowed_accounts {
+            if !endowed_accounts.iter().any(|(id, _)| id == account_id) {
+                endowed_accounts.push((account_id.clone(), self.endowment));
+            }
+        }
+        Genesis {
+            authorities: self.authorities.clone(),
+            endowed_accounts,
+        }
+    }
+
+    /// Builds the chain spec.
+    ///
+    /// Use `ChainSpec::as_json(true)` on the result to emit a raw spec.
+    pub fn build<G, F>(self, genesis: F) -> GenericChainSpec<G>
+    where
+        G: RuntimeGenesis + 'static,
+        F: Fn(&Genesis) -> G + Send + Sync + 'static,
+    {
+        let params = self.genesis();
+        let properties = if self.properties.is_empty() {
+            None
+        } else {
+            Some(self.properties)
+        };
+        GenericChainSpec::from_genesis(
+            &self.name,
+            &self.id,
+            self.chain_type,
+            move || genesis(&params),
+            self.boot_nodes,
+            None,
+            self.protocol_id.as_deref(),
+            properties,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use sc_service::ChainSpec;
+
+    #[test]
+    fn test_local_testnet() {
+        let builder = ChainSpecBuilder::local_testnet(3)
+            .unwrap()
+            .token("SUN", 12)
+            .endowment(1000);
+        let genesis = builder.genesis();
+        assert_eq!(genesis.aura_authorities().len(), 3);
+        assert_eq!(genesis.grandpa_authorities().len(), 3);
+        assert_eq!(
+            genesis.authorities[1],
+            Authority::from_keyring(Sr25519Keyring::Bob)
+        );
+        assert!(genesis.endowed_accounts.iter().all(|(_, b)| *b == 1000));
+
+        let spec = builder.build(mock::genesis);
+        assert_eq!(spec.properties()["tokenSymbol"], "SUN");
+        assert!(spec.as_json(true).is_ok());
+
+        assert!(ChainSpecBuilder::local_testnet(100).is_err());
+        assert!(Authority::from_suri("not a suri").is_err());
+    }
+}
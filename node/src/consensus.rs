@@ -0,0 +1,472 @@
+//! Consensus engines of the node service.
+use crate::builder::{
+    FullApi, FullBackend, FullClient, FullConsensus, FullParts, FullSelectChain, FullService,
+    ImportSetup, LightApi, LightBackend, LightClient, LightConsensus, LightParts,
+    RuntimeApiCollection,
+};
+use crate::rpc::RpcExtension;
+use futures::channel::mpsc;
+use futures::{FutureExt, StreamExt};
+use sc_client_api::{ExecutorProvider, StateBackendFor};
+use sc_consensus_manual_seal::rpc::{ManualSeal, ManualSealApi};
+use sc_consensus_manual_seal::{EngineCommand, ManualSealParams};
+use sc_executor::NativeExecutionDispatch;
+use sc_finality_grandpa::{BlockNumberOps, GrandpaBlockImport, LinkHalf};
+use sc_service::error::Error;
+use sc_service::{Configuration, TaskManager};
+use sp_api::ConstructRuntimeApi;
+use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+use sp_inherents::{InherentData, InherentIdentifier, ProvideInherentData};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use sp_transaction_pool::TransactionPool;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tiny_multihash::MultihashDigest;
+
+pub type AuraId = sp_consensus_aura::sr25519::AuthorityId;
+pub type GrandpaId = sp_finality_grandpa::AuthorityId;
+
+type GrandpaLink<B, RA, E> = (
+    GrandpaBlockImport<FullBackend<B>, B, FullClient<B, RA, E>, FullSelectChain<B>>,
+    LinkHalf<B, FullClient<B, RA, E>, FullSelectChain<B>>,
+);
+
+/// Aura block production with grandpa finality.
+pub struct AuraGrandpa<B: BlockT, RA, E> {
+    link: Option<GrandpaLink<B, RA, E>>,
+}
+
+impl<B: BlockT, RA, E> Default for AuraGrandpa<B, RA, E> {
+    fn default() -> Self {
+        Self { link: None }
+    }
+}
+
+impl<B, RA, E> FullConsensus<B, RA, E> for AuraGrandpa<B, RA, E>
+where
+    B: BlockT,
+    NumberFor<B>: BlockNumberOps,
+    E: NativeExecutionDispatch + 'static,
+    RA: ConstructRuntimeApi<B, FullClient<B, RA, E>> + Send + Sync + 'static,
+    FullApi<B, RA, E>: RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>
+        + sp_consensus_aura::AuraApi<B, AuraId>
+        + sp_finality_grandpa::GrandpaApi<B>,
+{
+    fn import_queue(
+        &mut self,
+        config: &Configuration,
+        parts: &FullParts<B, RA, E>,
+    ) -> Result<ImportSetup<B, FullClient<B, RA, E>>, Error> {
+        let client = &parts.client;
+        let (grandpa_block_import, grandpa_link) = sc_finality_grandpa::block_import(
+            client.clone(),
+            &(client.clone() as Arc<_>),
+            parts.select_chain.clone(),
+        )?;
+
+        let aura_block_import = sc_consensus_aura::AuraBlockImport::<_, _, _, AuraPair>::new(
+            grandpa_block_import.clone(),
+            client.clone(),
+        );
+
+        let import_queue = sc_consensus_aura::import_queue::<_, _, _, AuraPair, _, _>(
+            sc_consensus_aura::slot_duration(&**client)?,
+            aura_block_import,
+            Some(Box::new(grandpa_block_import.clone())),
+            None,
+            client.clone(),
+            parts.inherent_data_providers.clone(),
+            &parts.task_manager.spawn_handle(),
+            config.prometheus_registry(),
+            sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone()),
+        )?;
+
+        let finality_proof_provider = sc_finality_grandpa::FinalityProofProvider::new_for_service(
+            parts.backend.clone(),
+            client.clone(),
+        );
+
+        self.link = Some((grandpa_block_import, grandpa_link));
+        Ok(ImportSetup {
+            import_queue,
+            finality_proof_request_builder: None,
+            finality_proof_provider: Some(finality_proof_provider),
+        })
+    }
+
+    fn start<M: MultihashDigest>(
+        self,
+        service: FullService<B, RA, E, M>,
+        task_manager: &mut TaskManager,
+    ) -> Result<(), Error> {
+        let (block_import, grandpa_link) = self
+            .link
+            .ok_or_else(|| Error::Other("import queue wasn't created".into()))?;
+        let FullService {
+            client,
+            keystore,
+            select_chain,
+            transaction_pool,
+            inherent_data_providers,
+            network,
+            telemetry_connection_sinks,
+            prometheus_registry,
+            role,
+            force_authoring,
+            disable_grandpa,
+            name,
+            ..
+        } = service;
+
+        if role.is_authority() {
+            let proposer = sc_basic_authorship::ProposerFactory::new(
+                client.clone(),
+                transaction_pool,
+                prometheus_registry.as_ref(),
+            );
+
+            let can_author_with =
+                sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
+
+            let aura = sc_consensus_aura::start_aura::<_, _, _, _, _, AuraPair, _, _, _>(
+                sc_consensus_aura::slot_duration(&*client)?,
+                client.clone(),
+                select_chain,
+                block_import,
+                proposer,
+                network.clone(),
+                inherent_data_providers.clone(),
+                force_authoring,
+                keystore.clone(),
+                can_author_with,
+            )?;
+
+            // the AURA authoring task is considered essential, i.e. if it
+            // fails we take down the service with it.
+            task_manager
+                .spawn_essential_handle()
+                .spawn_blocking("aura", aura);
+        }
+
+        // if the node isn't actively participating in consensus then it doesn't
+        // need a keystore, regardless of which protocol we use below.
+        let keystore = if role.is_authority() {
+            Some(keystore as sp_core::traits::BareCryptoStorePtr)
+        } else {
+            None
+        };
+
+        let grandpa_config = sc_finality_grandpa::Config {
+            // FIXME #1578 make this available through chainspec
+            gossip_duration: Duration::from_millis(333),
+            justification_period: 512,
+            name: Some(name),
+            observer_enabled: false,
+            keystore,
+            is_authority: role.is_network_authority(),
+        };
+
+        if !disable_grandpa {
+            // start the full GRANDPA voter
+            // NOTE: non-authorities could run the GRANDPA observer protocol, but at
+            // this point the full voter should provide better guarantees of block
+            // and vote data availability than the observer. The observer has not
+            // been tested extensively yet and having most nodes in a network run it
+            // could lead to finality stalls.
+            let grandpa_config = sc_finality_grandpa::GrandpaParams {
+                config: grandpa_config,
+                link: grandpa_link,
+                network,
+                inherent_data_providers,
+                telemetry_on_connect: Some(telemetry_connection_sinks.on_connect_stream()),
+                voting_rule: sc_finality_grandpa::VotingRulesBuilder::default().build(),
+                prometheus_registry,
+                shared_voter_state: sc_finality_grandpa::SharedVoterState::empty(),
+            };
+
+            // the GRANDPA voter task is considered infallible, i.e.
+            // if it fails we take down the service with it.
+            task_manager.spawn_essential_handle().spawn_blocking(
+                "grandpa-voter",
+                sc_finality_grandpa::run_grandpa_voter(grandpa_config)?,
+            );
+        } else {
+            sc_finality_grandpa::setup_disabled_grandpa(client, &inherent_data_providers, network)?;
+        }
+        Ok(())
+    }
+}
+
+/// Aura block import with grandpa finality proofs for light clients.
+pub struct AuraGrandpaLight<B, RA, E> {
+    _marker: PhantomData<(B, RA, E)>,
+}
+
+impl<B, RA, E> Default for AuraGrandpaLight<B, RA, E> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, RA, E> LightConsensus<B, RA, E> for AuraGrandpaLight<B, RA, E>
+where
+    B: BlockT,
+    NumberFor<B>: BlockNumberOps,
+    E: NativeExecutionDispatch + 'static,
+    RA: ConstructRuntimeApi<B, LightClient<B, RA, E>> + Send + Sync + 'static,
+    LightApi<B, RA, E>: RuntimeApiCollection<B, StateBackend = StateBackendFor<LightBackend<B>, B>>
+        + sp_consensus_aura::AuraApi<B, AuraId>
+        + sp_finality_grandpa::GrandpaApi<B>,
+{
+    fn import_queue(
+        &mut self,
+        config: &Configuration,
+        parts: &LightParts<B, RA, E>,
+    ) -> Result<ImportSetup<B, LightClient<B, RA, E>>, Error> {
+        let client = &parts.client;
+        let grandpa_block_import = sc_finality_grandpa::light_block_import(
+            client.clone(),
+            parts.backend.clone(),
+            &(client.clone() as Arc<_>),
+            Arc::new(parts.on_demand.checker().clone()) as Arc<_>,
+        )?;
+        let finality_proof_import = grandpa_block_import.clone();
+        let finality_proof_request_builder =
+            finality_proof_import.create_finality_proof_request_builder();
+
+        let import_queue = sc_consensus_aura::import_queue::<_, _, _, AuraPair, _, _>(
+            sc_consensus_aura::slot_duration(&**client)?,
+            grandpa_block_import,
+            None,
+            Some(Box::new(finality_proof_import)),
+            client.clone(),
+            sp_inherents::InherentDataProviders::new(),
+            &parts.task_manager.spawn_handle(),
+            config.prometheus_registry(),
+            sp_consensus::NeverCanAuthor,
+        )?;
+
+        let finality_proof_provider = sc_finality_grandpa::FinalityProofProvider::new_for_service(
+            parts.backend.clone(),
+            client.clone(),
+        );
+
+        Ok(ImportSetup {
+            import_queue,
+            finality_proof_request_builder: Some(finality_proof_request_builder),
+            finality_proof_provider: Some(finality_proof_provider),
+        })
+    }
+}
+
+/// Block production for development nodes.
+///
+/// Blocks are sealed and finalized as soon as a transaction enters the pool
+/// (instant seal), or when requested through the `engine_createBlock` and
+/// `engine_finalizeBlock` rpc methods (manual seal). Block timestamps advance
+/// by the block time on every block, so runtimes checking the slot of aura
+/// accept them as long as the block time is at least the slot duration read
+/// from the runtime. Starting fails otherwise.
+pub struct DevSeal<B: BlockT> {
+    instant: bool,
+    block_time: Duration,
+    commands: (
+        mpsc::Sender<EngineCommand<B::Hash>>,
+        Option<mpsc::Receiver<EngineCommand<B::Hash>>>,
+    ),
+}
+
+impl<B: BlockT> DevSeal<B> {
+    fn new(instant: bool, block_time: Duration) -> Self {
+        let (tx, rx) = mpsc::channel(1024);
+        Self {
+            instant,
+            block_time,
+            commands: (tx, Some(rx)),
+        }
+    }
+
+    /// Seals a block for every transaction imported into the pool.
+    ///
+    /// Blocks can still be requested through rpc.
+    pub fn instant(block_time: Duration) -> Self {
+        Self::new(true, block_time)
+    }
+
+    /// Seals blocks only when requested through rpc.
+    pub fn manual(block_time: Duration) -> Self {
+        Self::new(false, block_time)
+    }
+}
+
+impl<B, RA, E> FullConsensus<B, RA, E> for DevSeal<B>
+where
+    B: BlockT,
+    E: NativeExecutionDispatch + 'static,
+    RA: ConstructRuntimeApi<B, FullClient<B, RA, E>> + Send + Sync + 'static,
+    FullApi<B, RA, E>: RuntimeApiCollection<B, StateBackend = StateBackendFor<FullBackend<B>, B>>
+        + sp_consensus_aura::AuraApi<B, AuraId>,
+{
+    fn import_queue(
+        &mut self,
+        config: &Configuration,
+        parts: &FullParts<B, RA, E>,
+    ) -> Result<ImportSetup<B, FullClient<B, RA, E>>, Error> {
+        let import_queue = sc_consensus_manual_seal::import_queue(
+            Box::new(parts.client.clone()),
+            &parts.task_manager.spawn_handle(),
+            config.prometheus_registry(),
+        );
+        Ok(ImportSetup {
+            import_queue,
+            finality_proof_request_builder: None,
+            finality_proof_provider: None,
+        })
+    }
+
+    fn rpc_extensions(&self) -> Option<Box<dyn Fn() -> RpcExtension + Send>> {
+        let commands = self.commands.0.clone();
+        Some(Box::new(move || {
+            let mut io = RpcExtension::default();
+            io.extend_with(ManualSealApi::to_delegate(ManualSeal::new(
+                commands.clone(),
+            )));
+            io
+        }))
+    }
+
+    fn start<M: MultihashDigest>(
+        mut self,
+        service: FullService<B, RA, E, M>,
+        task_manager: &mut TaskManager,
+    ) -> Result<(), Error> {
+        let FullService {
+            client,
+            select_chain,
+            transaction_pool,
+            inherent_data_providers,
+            prometheus_registry,
+            ..
+        } = service;
+
+        let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+        let slot_duration = Duration::from_millis(slot_duration.get());
+        let timestamp = BlockTimestamp::new(self.block_time, slot_duration)?;
+        inherent_data_providers
+            .register_provider(timestamp)
+            .map_err(|err| Error::Other(format!("{:?}", err)))?;
+
+        let rpc_commands = self
+            .commands
+            .1
+            .take()
+            .ok_or_else(|| Error::Other("dev seal was already started".into()))?;
+        let commands_stream = if self.instant {
+            let pool_commands = transaction_pool.import_notification_stream().map(|_| {
+                EngineCommand::SealNewBlock {
+                    create_empty: false,
+                    finalize: true,
+                    parent_hash: None,
+                    sender: None,
+                }
+            });
+            futures::stream::select(rpc_commands, pool_commands).boxed()
+        } else {
+            rpc_commands.boxed()
+        };
+
+        let proposer = sc_basic_authorship::ProposerFactory::new(
+            client.clone(),
+            transaction_pool.clone(),
+            prometheus_registry.as_ref(),
+        );
+
+        let seal = sc_consensus_manual_seal::run_manual_seal(ManualSealParams {
+            block_import: client.clone(),
+            env: proposer,
+            client,
+            pool: transaction_pool.pool().clone(),
+            commands_stream,
+            select_chain,
+            consensus_data_provider: None,
+            inherent_data_providers,
+        });
+
+        task_manager
+            .spawn_essential_handle()
+            .spawn_blocking("dev-seal", seal);
+        Ok(())
+    }
+}
+
+/// Timestamp inherent advancing by the block time on every block.
+struct BlockTimestamp {
+    next: AtomicU64,
+    block_time: u64,
+}
+
+impl BlockTimestamp {
+    fn new(block_time: Duration, slot_duration: Duration) -> Result<Self, Error> {
+        if slot_duration.as_millis() == 0 || block_time < slot_duration {
+            return Err(Error::Other(format!(
+                "block time of {:?} is shorter than the slot duration of {:?}",
+                block_time, slot_duration
+            )));
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let block_time = block_time.as_millis() as u64;
+        Ok(Self {
+            next: AtomicU64::new(now.as_millis() as u64 / block_time * block_time),
+            block_time,
+        })
+    }
+}
+
+impl ProvideInherentData for BlockTimestamp {
+    fn inherent_identifier(&self) -> &'static InherentIdentifier {
+        &sp_timestamp::INHERENT_IDENTIFIER
+    }
+
+    fn provide_inherent_data(&self, data: &mut InherentData) -> Result<(), sp_inherents::Error> {
+        let timestamp = self.next.fetch_add(self.block_time, Ordering::SeqCst);
+        data.put_data(sp_timestamp::INHERENT_IDENTIFIER, &timestamp)
+    }
+
+    fn error_to_string(&self, error: &[u8]) -> Option<String> {
+        Some(format!("{:?}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_timestamp() {
+        let slot = Duration::from_millis(1000);
+        assert!(BlockTimestamp::new(Duration::from_millis(0), slot).is_err());
+        assert!(BlockTimestamp::new(slot / 2, slot).is_err());
+        assert!(BlockTimestamp::new(slot, Duration::from_millis(0)).is_err());
+
+        let timestamp = BlockTimestamp::new(slot * 2, slot).unwrap();
+        let mut data = InherentData::new();
+        timestamp.provide_inherent_data(&mut data).unwrap();
+        let first: u64 = data
+            .get_data(&sp_timestamp::INHERENT_IDENTIFIER)
+            .unwrap()
+            .unwrap();
+        timestamp.provide_inherent_data(&mut data).unwrap();
+        let second: u64 = data
+            .get_data(&sp_timestamp::INHERENT_IDENTIFIER)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first % 2000, 0);
+        assert_eq!(second - first, 2000);
+    }
+}
@@ -1,5 +1,6 @@
 //! Default node service implementation.
 
+pub use futures;
 pub use sc_basic_authorship;
 pub use sc_client_api;
 pub use sc_consensus;
@@ -15,105 +16,66 @@ pub use sp_finality_grandpa;
 pub use sp_inherents;
 pub use tiny_multihash;
 
+pub mod builder;
+pub mod chain_spec;
+pub mod consensus;
+pub mod rpc;
+
+/// Generates the service functions of a node.
+///
+/// Thin wrapper around [`builder::NodeBuilder`] using aura and grandpa,
+/// kept for compatibility. Use the builder directly to customize the node.
 #[macro_export]
 macro_rules! node_service {
     ($block:ty, $api:ty, $executor:ty) => {
-        use sc_client_api::{ExecutorProvider, RemoteBackend};
         use sc_network::NetworkService;
-        use sc_service::{Configuration, PartialComponents, RpcHandlers, TaskManager};
-        use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+        use sc_service::{Configuration, RpcHandlers, TaskManager};
         use sp_runtime::traits::Block;
         use std::sync::Arc;
-        use std::time::Duration;
         use tiny_multihash::MultihashDigest;
-        use $crate::{
-            sc_basic_authorship, sc_client_api, sc_consensus, sc_consensus_aura,
-            sc_finality_grandpa, sc_network, sc_service, sc_transaction_pool, sp_consensus,
-            sp_consensus_aura, sp_core, sp_finality_grandpa, sp_inherents, tiny_multihash,
-        };
+        use $crate::consensus::AuraGrandpa;
+        use $crate::rpc::{OffchainStoreHandle, RpcDeps, RpcExtension};
+        use $crate::{sc_network, sc_service, sp_core, tiny_multihash};
+
+        type FullClient = $crate::builder::FullClient<$block, $api, $executor>;
+        type FullPool = $crate::builder::FullPool<$block, $api, $executor>;
+        type LightClient = $crate::builder::LightClient<$block, $api, $executor>;
+        type LightPool = $crate::builder::LightPool<$block, $api, $executor>;
 
-        type FullClient = sc_service::TFullClient<$block, $api, $executor>;
-        type FullBackend = sc_service::TFullBackend<$block>;
-        type FullSelectChain = sc_consensus::LongestChain<FullBackend, $block>;
+        pub type AuraId = $crate::consensus::AuraId;
+        pub type GrandpaId = $crate::consensus::GrandpaId;
 
-        pub type AuraId = sp_consensus_aura::sr25519::AuthorityId;
-        pub type GrandpaId = sp_finality_grandpa::AuthorityId;
+        /// Builder of the node service.
+        pub type NodeBuilder = $crate::builder::NodeBuilder<$block, $api, $executor>;
 
         pub fn new_partial(
             config: &Configuration,
+        ) -> Result<$crate::builder::FullPartial<$block, $api, $executor>, sc_service::error::Error>
+        {
+            $crate::builder::new_partial(config, &mut AuraGrandpa::default())
+        }
+
+        /// Builds a new service for a full client with the default rpc extensions.
+        ///
+        /// The offchain rpc methods serve an empty store.
+        pub fn new_full<M: MultihashDigest>(
+            config: Configuration,
         ) -> Result<
-            sc_service::PartialComponents<
-                FullClient,
-                FullBackend,
-                FullSelectChain,
-                sp_consensus::DefaultImportQueue<$block, FullClient>,
-                sc_transaction_pool::FullPool<$block, FullClient>,
-                (
-                    sc_finality_grandpa::GrandpaBlockImport<
-                        FullBackend,
-                        $block,
-                        FullClient,
-                        FullSelectChain,
-                    >,
-                    sc_finality_grandpa::LinkHalf<$block, FullClient, FullSelectChain>,
-                ),
-            >,
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
             sc_service::error::Error,
         > {
-            let inherent_data_providers = sp_inherents::InherentDataProviders::new();
-
-            let (client, backend, keystore, task_manager) =
-                sc_service::new_full_parts::<$block, $api, $executor>(&config)?;
-            let client = Arc::new(client);
-
-            let select_chain = sc_consensus::LongestChain::new(backend.clone());
-
-            let transaction_pool = sc_transaction_pool::BasicPool::new_full(
-                config.transaction_pool.clone(),
-                config.prometheus_registry(),
-                task_manager.spawn_handle(),
-                client.clone(),
-            );
-
-            let (grandpa_block_import, grandpa_link) = sc_finality_grandpa::block_import(
-                client.clone(),
-                &(client.clone() as Arc<_>),
-                select_chain.clone(),
-            )?;
-
-            let aura_block_import = sc_consensus_aura::AuraBlockImport::<_, _, _, AuraPair>::new(
-                grandpa_block_import.clone(),
-                client.clone(),
-            );
-
-            let import_queue = sc_consensus_aura::import_queue::<_, _, _, AuraPair, _, _>(
-                sc_consensus_aura::slot_duration(&*client)?,
-                aura_block_import,
-                Some(Box::new(grandpa_block_import.clone())),
-                None,
-                client.clone(),
-                inherent_data_providers.clone(),
-                &task_manager.spawn_handle(),
-                config.prometheus_registry(),
-                sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone()),
-            )?;
-
-            Ok(sc_service::PartialComponents {
-                client,
-                backend,
-                task_manager,
-                import_queue,
-                keystore,
-                select_chain,
-                transaction_pool,
-                inherent_data_providers,
-                other: (grandpa_block_import, grandpa_link),
-            })
+            NodeBuilder::new(config).full()
         }
 
-        /// Builds a new service for a full client.
-        pub fn new_full<M: MultihashDigest>(
+        /// Builds a new service for a full client with the default rpc extensions
+        /// serving the store set on the `offchain` handle.
+        pub fn new_full_with_offchain<M: MultihashDigest>(
             config: Configuration,
+            offchain: OffchainStoreHandle,
         ) -> Result<
             (
                 TaskManager,
@@ -122,156 +84,73 @@ macro_rules! node_service {
             ),
             sc_service::error::Error,
         > {
-            let PartialComponents {
-                client,
-                backend,
-                mut task_manager,
-                import_queue,
-                keystore,
-                select_chain,
-                transaction_pool,
-                inherent_data_providers,
-                other: (block_import, grandpa_link),
-            } = new_partial(&config)?;
-
-            let finality_proof_provider =
-                sc_finality_grandpa::FinalityProofProvider::new_for_service(
-                    backend.clone(),
-                    client.clone(),
-                );
-
-            let (network, network_status_sinks, system_rpc_tx, network_starter) =
-                sc_service::BuildNetworkParams {
-                    config: &config,
-                    client: client.clone(),
-                    transaction_pool: transaction_pool.clone(),
-                    spawn_handle: task_manager.spawn_handle(),
-                    import_queue,
-                    on_demand: None,
-                    block_announce_validator_builder: None,
-                    finality_proof_request_builder: None,
-                    finality_proof_provider: Some(finality_proof_provider.clone()),
-                }
-                .build_network::<M>()?;
-
-            if config.offchain_worker.enabled {
-                sc_service::build_offchain_workers(
-                    &config,
-                    backend.clone(),
-                    task_manager.spawn_handle(),
-                    client.clone(),
-                    network.clone(),
-                );
-            }
-
-            let role = config.role.clone();
-            let force_authoring = config.force_authoring;
-            let name = config.network.node_name.clone();
-            let enable_grandpa = !config.disable_grandpa;
-            let prometheus_registry = config.prometheus_registry().cloned();
-            let telemetry_connection_sinks = sc_service::TelemetryConnectionSinks::default();
-
-            let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
-                network: network.clone(),
-                client: client.clone(),
-                keystore: keystore.clone(),
-                task_manager: &mut task_manager,
-                transaction_pool: transaction_pool.clone(),
-                telemetry_connection_sinks: telemetry_connection_sinks.clone(),
-                rpc_extensions_builder: Box::new(|_, _| ()),
-                on_demand: None,
-                remote_blockchain: None,
-                backend,
-                network_status_sinks,
-                system_rpc_tx,
-                config,
-            })?;
-
-            if role.is_authority() {
-                let proposer = sc_basic_authorship::ProposerFactory::new(
-                    client.clone(),
-                    transaction_pool,
-                    prometheus_registry.as_ref(),
-                );
-
-                let can_author_with =
-                    sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
-
-                let aura = sc_consensus_aura::start_aura::<_, _, _, _, _, AuraPair, _, _, _>(
-                    sc_consensus_aura::slot_duration(&*client)?,
-                    client.clone(),
-                    select_chain,
-                    block_import,
-                    proposer,
-                    network.clone(),
-                    inherent_data_providers.clone(),
-                    force_authoring,
-                    keystore.clone(),
-                    can_author_with,
-                )?;
-
-                // the AURA authoring task is considered essential, i.e. if it
-                // fails we take down the service with it.
-                task_manager
-                    .spawn_essential_handle()
-                    .spawn_blocking("aura", aura);
-            }
-
-            // if the node isn't actively participating in consensus then it doesn't
-            // need a keystore, regardless of which protocol we use below.
-            let keystore = if role.is_authority() {
-                Some(keystore as sp_core::traits::BareCryptoStorePtr)
-            } else {
-                None
-            };
-
-            let grandpa_config = sc_finality_grandpa::Config {
-                // FIXME #1578 make this available through chainspec
-                gossip_duration: Duration::from_millis(333),
-                justification_period: 512,
-                name: Some(name),
-                observer_enabled: false,
-                keystore,
-                is_authority: role.is_network_authority(),
-            };
+            NodeBuilder::new(config).offchain_store(offchain).full()
+        }
 
-            if enable_grandpa {
-                // start the full GRANDPA voter
-                // NOTE: non-authorities could run the GRANDPA observer protocol, but at
-                // this point the full voter should provide better guarantees of block
-                // and vote data availability than the observer. The observer has not
-                // been tested extensively yet and having most nodes in a network run it
-                // could lead to finality stalls.
-                let grandpa_config = sc_finality_grandpa::GrandpaParams {
-                    config: grandpa_config,
-                    link: grandpa_link,
-                    network: network.clone(),
-                    inherent_data_providers,
-                    telemetry_on_connect: Some(telemetry_connection_sinks.on_connect_stream()),
-                    voting_rule: sc_finality_grandpa::VotingRulesBuilder::default().build(),
-                    prometheus_registry,
-                    shared_voter_state: sc_finality_grandpa::SharedVoterState::empty(),
-                };
+        /// Builds a new service for a full client with custom rpc extensions.
+        pub fn new_full_with_rpc<M, F>(
+            config: Configuration,
+            offchain: OffchainStoreHandle,
+            rpc: F,
+        ) -> Result<
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
+            sc_service::error::Error,
+        >
+        where
+            M: MultihashDigest,
+            F: Fn(RpcDeps<FullClient, FullPool>) -> RpcExtension + Send + 'static,
+        {
+            NodeBuilder::new(config)
+                .offchain_store(offchain)
+                .full_rpc(rpc)
+                .full()
+        }
 
-                // the GRANDPA voter task is considered infallible, i.e.
-                // if it fails we take down the service with it.
-                task_manager.spawn_essential_handle().spawn_blocking(
-                    "grandpa-voter",
-                    sc_finality_grandpa::run_grandpa_voter(grandpa_config)?,
-                );
-            } else {
-                sc_finality_grandpa::setup_disabled_grandpa(
-                    client,
-                    &inherent_data_providers,
-                    network.clone(),
-                )?;
-            }
+        /// Builds a new service for a development node.
+        ///
+        /// Blocks are sealed and finalized instantly when a transaction is
+        /// submitted, with timestamps advancing by `block_time`. Fails if the
+        /// block time is shorter than the slot duration of the runtime.
+        pub fn new_dev<M: MultihashDigest>(
+            config: Configuration,
+            block_time: std::time::Duration,
+        ) -> Result<
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
+            sc_service::error::Error,
+        > {
+            new_dev_with_offchain(config, Default::default(), block_time)
+        }
 
-            network_starter.start_network();
-            Ok((task_manager, rpc_handlers, network))
+        /// Builds a new service for a development node serving the store set on
+        /// the `offchain` handle.
+        pub fn new_dev_with_offchain<M: MultihashDigest>(
+            config: Configuration,
+            offchain: OffchainStoreHandle,
+            block_time: std::time::Duration,
+        ) -> Result<
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
+            sc_service::error::Error,
+        > {
+            NodeBuilder::new(config)
+                .offchain_store(offchain)
+                .build_full($crate::consensus::DevSeal::instant(block_time))
         }
 
-        /// Builds a new service for a light client.
+        /// Builds a new service for a light client with the default rpc extensions.
+        ///
+        /// The offchain rpc methods serve an empty store.
         pub fn new_light<M: MultihashDigest>(
             config: Configuration,
         ) -> Result<
@@ -282,87 +161,86 @@ macro_rules! node_service {
             ),
             sc_service::error::Error,
         > {
-            let (client, backend, keystore, mut task_manager, on_demand) =
-                sc_service::new_light_parts::<$block, $api, $executor>(&config)?;
-
-            let transaction_pool = Arc::new(sc_transaction_pool::BasicPool::new_light(
-                config.transaction_pool.clone(),
-                config.prometheus_registry(),
-                task_manager.spawn_handle(),
-                client.clone(),
-                on_demand.clone(),
-            ));
-
-            let grandpa_block_import = sc_finality_grandpa::light_block_import(
-                client.clone(),
-                backend.clone(),
-                &(client.clone() as Arc<_>),
-                Arc::new(on_demand.checker().clone()) as Arc<_>,
-            )?;
-            let finality_proof_import = grandpa_block_import.clone();
-            let finality_proof_request_builder =
-                finality_proof_import.create_finality_proof_request_builder();
+            NodeBuilder::new(config).light()
+        }
 
-            let import_queue = sc_consensus_aura::import_queue::<_, _, _, AuraPair, _, _>(
-                sc_consensus_aura::slot_duration(&*client)?,
-                grandpa_block_import,
-                None,
-                Some(Box::new(finality_proof_import)),
-                client.clone(),
-                sp_inherents::InherentDataProviders::new(),
-                &task_manager.spawn_handle(),
-                config.prometheus_registry(),
-                sp_consensus::NeverCanAuthor,
-            )?;
+        /// Builds a new service for a light client with the default rpc extensions
+        /// serving the store set on the `offchain` handle.
+        pub fn new_light_with_offchain<M: MultihashDigest>(
+            config: Configuration,
+            offchain: OffchainStoreHandle,
+        ) -> Result<
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
+            sc_service::error::Error,
+        > {
+            NodeBuilder::new(config).offchain_store(offchain).light()
+        }
 
-            let finality_proof_provider =
-                sc_finality_grandpa::FinalityProofProvider::new_for_service(
-                    backend.clone(),
-                    client.clone(),
-                );
+        /// Builds a new service for a light client with custom rpc extensions.
+        pub fn new_light_with_rpc<M, F>(
+            config: Configuration,
+            offchain: OffchainStoreHandle,
+            rpc: F,
+        ) -> Result<
+            (
+                TaskManager,
+                RpcHandlers,
+                Arc<NetworkService<$block, <$block as Block>::Hash, M>>,
+            ),
+            sc_service::error::Error,
+        >
+        where
+            M: MultihashDigest,
+            F: Fn(RpcDeps<LightClient, LightPool>) -> RpcExtension + Send + 'static,
+        {
+            NodeBuilder::new(config)
+                .offchain_store(offchain)
+                .light_rpc(rpc)
+                .light()
+        }
 
-            let (network, network_status_sinks, system_rpc_tx, network_starter) =
-                sc_service::BuildNetworkParams {
-                    config: &config,
-                    client: client.clone(),
-                    transaction_pool: transaction_pool.clone(),
-                    spawn_handle: task_manager.spawn_handle(),
-                    import_queue,
-                    on_demand: Some(on_demand.clone()),
-                    block_announce_validator_builder: None,
-                    finality_proof_request_builder: Some(finality_proof_request_builder),
-                    finality_proof_provider: Some(finality_proof_provider),
-                }
-                .build_network::<M>()?;
+        /// Exports the blocks in the range from the database to a file.
+        pub fn export_blocks(
+            config: Configuration,
+            output: &std::path::Path,
+            from: u32,
+            to: Option<u32>,
+            binary: bool,
+        ) -> Result<(), sc_service::error::Error> {
+            NodeBuilder::new(config).export_blocks(AuraGrandpa::default(), output, from, to, binary)
+        }
 
-            if config.offchain_worker.enabled {
-                sc_service::build_offchain_workers(
-                    &config,
-                    backend.clone(),
-                    task_manager.spawn_handle(),
-                    client.clone(),
-                    network.clone(),
-                );
-            }
+        /// Imports blocks from a file into the database.
+        pub fn import_blocks(
+            config: Configuration,
+            input: &std::path::Path,
+            binary: bool,
+        ) -> Result<(), sc_service::error::Error> {
+            NodeBuilder::new(config).import_blocks(AuraGrandpa::default(), input, binary)
+        }
 
-            let rpc_handlers = sc_service::spawn_tasks(sc_service::SpawnTasksParams {
-                remote_blockchain: Some(backend.remote_blockchain()),
-                transaction_pool,
-                task_manager: &mut task_manager,
-                on_demand: Some(on_demand),
-                rpc_extensions_builder: Box::new(|_, _| ()),
-                telemetry_connection_sinks: sc_service::TelemetryConnectionSinks::default(),
-                config,
-                client,
-                keystore,
-                backend,
-                network: network.clone(),
-                network_status_sinks,
-                system_rpc_tx,
-            })?;
+        /// Reimports a block given by number or hex encoded hash.
+        pub fn check_block(
+            config: Configuration,
+            block: &str,
+        ) -> Result<(), sc_service::error::Error> {
+            use sp_runtime::codec::Decode;
+            use sp_runtime::generic::BlockId;
 
-            network_starter.start_network();
-            Ok((task_manager, rpc_handlers, network))
+            let block_id = if let Ok(number) = block.parse::<u32>() {
+                BlockId::Number(number.into())
+            } else {
+                let bytes = sp_core::bytes::from_hex(block)
+                    .map_err(|err| sc_service::error::Error::Other(format!("{:?}", err)))?;
+                let hash = Decode::decode(&mut &bytes[..])
+                    .map_err(|err| sc_service::error::Error::Other(err.to_string()))?;
+                BlockId::<$block>::Hash(hash)
+            };
+            NodeBuilder::new(config).check_block(AuraGrandpa::default(), block_id)
         }
     };
 }
@@ -380,22 +258,41 @@ pub mod mock {
     node_service!(runtime::OpaqueBlock, runtime::RuntimeApi, Executor);
     pub type ChainSpec = sc_service::GenericChainSpec<runtime::GenesisConfig>;
 
+    /// Genesis config of the mock runtime.
+    pub fn genesis(params: &crate::chain_spec::Genesis) -> runtime::GenesisConfig {
+        runtime::GenesisConfig {
+            frame_system: Some(runtime::SystemConfig {
+                code: runtime::WASM_BINARY.unwrap().to_vec(),
+                changes_trie_config: Default::default(),
+            }),
+            pallet_aura: Some(runtime::AuraConfig {
+                authorities: params.aura_authorities(),
+            }),
+            pallet_grandpa: Some(runtime::GrandpaConfig {
+                authorities: params.grandpa_authorities(),
+            }),
+            pallet_balances: Some(runtime::BalancesConfig {
+                balances: params.endowed_accounts.clone(),
+            }),
+        }
+    }
+
+    /// Chain spec without authorities or endowed accounts.
     pub fn empty_chain_spec() -> ChainSpec {
-        ChainSpec::from_genesis(
-            "empty",
-            "empty",
-            sc_service::ChainType::Development,
-            || runtime::GenesisConfig {
-                frame_system: Some(runtime::SystemConfig {
-                    code: runtime::WASM_BINARY.unwrap().to_vec(),
-                    changes_trie_config: Default::default(),
-                }),
-            },
-            vec![],
-            None,
-            None,
-            None,
-            None,
-        )
+        crate::chain_spec::ChainSpecBuilder::new("empty", "empty")
+            .chain_type(sc_service::ChainType::Development)
+            .build(genesis)
+    }
+
+    /// Development chain with `Alice` as the only authority.
+    pub fn dev_chain_spec() -> ChainSpec {
+        crate::chain_spec::ChainSpecBuilder::dev().build(genesis)
+    }
+
+    /// Local testnet with `n` keyring authorities.
+    pub fn testnet_chain_spec(
+        n: usize,
+    ) -> Result<ChainSpec, crate::chain_spec::TooManyAuthorities> {
+        Ok(crate::chain_spec::ChainSpecBuilder::local_testnet(n)?.build(genesis))
     }
 }
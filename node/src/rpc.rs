@@ -0,0 +1,287 @@
+//! Rpc extensions of the node service.
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::{Backend, StorageProvider};
+use sp_blockchain::HeaderBackend;
+use sp_core::storage::StorageKey;
+use sp_core::{blake2_128, twox_128, Bytes};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::Block;
+use sp_runtime::AccountId32;
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use sunshine_codec::{Cid, Multihash};
+use tiny_multihash::MultihashDigest;
+
+pub use sc_rpc_api::{DenyUnsafe, Metadata};
+
+/// Rpc methods added to the node.
+pub type RpcExtension = IoHandler<Metadata>;
+
+/// Dependencies of the rpc extensions.
+pub struct RpcDeps<C, P> {
+    /// Substrate client.
+    pub client: Arc<C>,
+    /// Transaction pool.
+    pub pool: Arc<P>,
+    /// Whether to deny unsafe calls.
+    pub deny_unsafe: DenyUnsafe,
+    /// Offchain block store.
+    pub offchain: OffchainStoreHandle,
+}
+
+/// Block store served by the `offchain_getBlock` and `offchain_putBlock`
+/// rpc methods.
+pub trait OffchainBlockStore: Send + Sync + 'static {
+    fn get(&self, cid: &[u8]) -> std::result::Result<Option<Vec<u8>>, String>;
+    fn insert(&self, cid: &[u8], data: Vec<u8>) -> std::result::Result<(), String>;
+}
+
+/// Shared handle to the offchain block store.
+///
+/// The store is created after the node started, so it is set through the
+/// handle once it is available. Until then the offchain rpc methods fail.
+#[derive(Clone, Default)]
+pub struct OffchainStoreHandle(Arc<RwLock<Option<Arc<dyn OffchainBlockStore>>>>);
+
+impl OffchainStoreHandle {
+    pub fn set(&self, store: Arc<dyn OffchainBlockStore>) {
+        *self.0.write().unwrap() = Some(store);
+    }
+
+    pub fn get(&self) -> Option<Arc<dyn OffchainBlockStore>> {
+        self.0.read().unwrap().clone()
+    }
+}
+
+fn rpc_error(message: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(1),
+        message,
+        data: None,
+    }
+}
+
+#[rpc(server)]
+pub trait OffchainApi {
+    /// Returns the block with the cid from the offchain store.
+    #[rpc(name = "offchain_getBlock")]
+    fn get_block(&self, cid: Bytes) -> Result<Option<Bytes>>;
+
+    /// Inserts a block into the offchain store.
+    ///
+    /// The block is rejected if its data doesn't hash to the cid.
+    #[rpc(name = "offchain_putBlock")]
+    fn put_block(&self, cid: Bytes, data: Bytes) -> Result<()>;
+}
+
+pub struct Offchain {
+    store: OffchainStoreHandle,
+    deny_unsafe: DenyUnsafe,
+}
+
+impl Offchain {
+    pub fn new(store: OffchainStoreHandle, deny_unsafe: DenyUnsafe) -> Self {
+        Self { store, deny_unsafe }
+    }
+
+    fn store(&self) -> Result<Arc<dyn OffchainBlockStore>> {
+        self.store
+            .get()
+            .ok_or_else(|| rpc_error("offchain store unavailable".into()))
+    }
+}
+
+impl OffchainApi for Offchain {
+    fn get_block(&self, cid: Bytes) -> Result<Option<Bytes>> {
+        let data = self.store()?.get(&cid).map_err(rpc_error)?;
+        Ok(data.map(Into::into))
+    }
+
+    fn put_block(&self, cid: Bytes, data: Bytes) -> Result<()> {
+        self.deny_unsafe.check_if_safe()?;
+        let parsed = Cid::try_from(&cid[..]).map_err(|err| rpc_error(err.to_string()))?;
+        let hash = Multihash::new(parsed.hash().code(), &data)
+            .map_err(|err| rpc_error(err.to_string()))?;
+        if hash.digest() != parsed.hash().digest() {
+            return Err(rpc_error("block data doesn't match the cid".into()));
+        }
+        self.store()?.insert(&cid, data.0).map_err(rpc_error)
+    }
+}
+
+#[rpc(server)]
+pub trait AccountApi {
+    /// Returns the nonce of the account at the best block.
+    ///
+    /// Transactions in the pool aren't included.
+    #[rpc(name = "account_nonce")]
+    fn nonce(&self, account: AccountId32) -> Result<u32>;
+
+    /// Returns the free balance of the account at the best block.
+    #[rpc(name = "account_freeBalance")]
+    fn free_balance(&self, account: AccountId32) -> Result<u128>;
+}
+
+/// Account helpers reading the `System::Account` storage.
+///
+/// Assumes the runtime uses `u32` nonces and the `u128` account data of
+/// `pallet_balances`.
+pub struct Accounts<B, BE, C> {
+    client: Arc<C>,
+    _marker: PhantomData<(B, BE)>,
+}
+
+impl<B, BE, C> Accounts<B, BE, C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, BE, C> Accounts<B, BE, C>
+where
+    B: Block,
+    BE: Backend<B>,
+    C: StorageProvider<B, BE> + HeaderBackend<B>,
+{
+    fn account(&self, account: AccountId32) -> Result<(u32, u32, u128)> {
+        let account = account.encode();
+        let key = StorageKey(
+            [
+                &twox_128(b"System")[..],
+                &twox_128(b"Account")[..],
+                &blake2_128(&account)[..],
+                &account[..],
+            ]
+            .concat(),
+        );
+        let best = BlockId::Hash(self.client.info().best_hash);
+        let data = self
+            .client
+            .storage(&best, &key)
+            .map_err(|err| rpc_error(err.to_string()))?;
+        if let Some(data) = data {
+            Decode::decode(&mut &data.0[..]).map_err(|err| rpc_error(err.to_string()))
+        } else {
+            Ok(Default::default())
+        }
+    }
+}
+
+impl<B, BE, C> AccountApi for Accounts<B, BE, C>
+where
+    B: Block,
+    BE: Backend<B> + 'static,
+    C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+{
+    fn nonce(&self, account: AccountId32) -> Result<u32> {
+        Ok(self.account(account)?.0)
+    }
+
+    fn free_balance(&self, account: AccountId32) -> Result<u128> {
+        Ok(self.account(account)?.2)
+    }
+}
+
+/// Default rpc extensions of a full node.
+pub fn full_extensions<B, BE, C, P>(deps: RpcDeps<C, P>) -> RpcExtension
+where
+    B: Block,
+    BE: Backend<B> + 'static,
+    C: StorageProvider<B, BE> + HeaderBackend<B> + Send + Sync + 'static,
+{
+    let mut io = IoHandler::default();
+    io.extend_with(OffchainApi::to_delegate(Offchain::new(
+        deps.offchain,
+        deps.deny_unsafe,
+    )));
+    io.extend_with(AccountApi::to_delegate(Accounts::new(deps.client)));
+    io
+}
+
+/// Default rpc extensions of a light client.
+///
+/// Light clients don't have the state, so only the offchain methods are
+/// included.
+pub fn light_extensions<C, P>(deps: RpcDeps<C, P>) -> RpcExtension {
+    let mut io = IoHandler::default();
+    io.extend_with(OffchainApi::to_delegate(Offchain::new(
+        deps.offchain,
+        deps.deny_unsafe,
+    )));
+    io
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use sunshine_codec::hasher::BLAKE2B_256;
+    use tiny_multihash::RawMultihash;
+
+    #[derive(Default)]
+    struct MemStore(Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+    impl OffchainBlockStore for MemStore {
+        fn get(&self, cid: &[u8]) -> std::result::Result<Option<Vec<u8>>, String> {
+            Ok(self.0.lock().unwrap().get(cid).cloned())
+        }
+
+        fn insert(&self, cid: &[u8], data: Vec<u8>) -> std::result::Result<(), String> {
+            self.0.lock().unwrap().insert(cid.to_vec(), data);
+            Ok(())
+        }
+    }
+
+    fn cid(data: &[u8]) -> Bytes {
+        let hash = Multihash::new(BLAKE2B_256, data).unwrap();
+        let hash = RawMultihash::wrap(BLAKE2B_256, hash.digest()).unwrap();
+        Cid::new_v1(0x55, hash).to_bytes().into()
+    }
+
+    fn with_store(deny_unsafe: DenyUnsafe) -> Offchain {
+        let handle = OffchainStoreHandle::default();
+        handle.set(Arc::new(MemStore::default()));
+        Offchain::new(handle, deny_unsafe)
+    }
+
+    #[test]
+    fn test_get_block() {
+        let offchain = Offchain::new(OffchainStoreHandle::default(), DenyUnsafe::No);
+        assert!(offchain.get_block(cid(b"block")).is_err());
+
+        let offchain = with_store(DenyUnsafe::No);
+        assert_eq!(offchain.get_block(cid(b"block")).unwrap(), None);
+        offchain
+            .put_block(cid(b"block"), b"block".to_vec().into())
+            .unwrap();
+        assert_eq!(
+            offchain.get_block(cid(b"block")).unwrap(),
+            Some(b"block".to_vec().into())
+        );
+    }
+
+    #[test]
+    fn test_put_block() {
+        let offchain = with_store(DenyUnsafe::No);
+        let invalid_cid = b"not a cid".to_vec().into();
+        assert!(offchain
+            .put_block(invalid_cid, b"block".to_vec().into())
+            .is_err());
+        assert!(offchain
+            .put_block(cid(b"block"), b"other".to_vec().into())
+            .is_err());
+        assert_eq!(offchain.get_block(cid(b"block")).unwrap(), None);
+
+        let offchain = with_store(DenyUnsafe::Yes);
+        assert!(offchain
+            .put_block(cid(b"block"), b"block".to_vec().into())
+            .is_err());
+    }
+}